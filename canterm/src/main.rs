@@ -18,14 +18,40 @@ use input_widget::InputWidget;
 use interpret::interpret;
 use list_widget::ListWidgets;
 
+/// Time to wait for the answers of the bridges
+const DISCOVER_TIMEOUT: Duration = Duration::from_secs(1);
+
 fn main() -> Result<()> {
     color_eyre::install()?;
+    if std::env::args().any(|arg| arg == "--discover") {
+        return discover();
+    }
     let terminal = ratatui::init();
     let app_result = App::new().run(terminal);
     ratatui::restore();
     app_result
 }
 
+/// Lists the bridges in the local network, the address can be copied into config.toml
+fn discover() -> Result<()> {
+    let bridges = corelib::discover(DISCOVER_TIMEOUT)?;
+    if bridges.is_empty() {
+        println!("No bridge found");
+    }
+    for bridge in bridges {
+        let endpoint = bridge.endpoint();
+        let [a, b, c, d] = endpoint.addr();
+        println!(
+            "{}  {a}.{b}.{c}.{d}:{}  firmware {}  {} bit/s",
+            bridge.name(),
+            endpoint.port(),
+            bridge.version(),
+            bridge.bitrate()
+        );
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct App {
     input_widget: InputWidget,
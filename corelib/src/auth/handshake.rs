@@ -0,0 +1,133 @@
+use crate::{ComItem, Error, Nonce, Secret};
+
+/// Challenge-response authentication of a session
+///
+/// The bridge sends the nonce with `$nonce` when the session starts. The host answers
+/// with `$auth` and the HMAC-SHA256 of the nonce, keyed with the shared secret.
+/// Without a secret every session is authenticated, a session stays authenticated
+/// when a secret is set later.
+pub struct Handshake {
+    nonce: Nonce,
+    authenticated: bool,
+}
+
+impl Handshake {
+    pub fn new(nonce: Nonce) -> Self {
+        Self {
+            nonce,
+            authenticated: false,
+        }
+    }
+
+    pub fn challenge(&self) -> ComItem {
+        ComItem::Nonce(self.nonce)
+    }
+
+    pub fn is_authenticated(&self, secret: Option<&Secret>) -> bool {
+        self.authenticated || secret.is_none()
+    }
+
+    /// Returns the item if it may be processed, `$auth` is consumed here
+    ///
+    /// A wrong answer returns `AuthFailed`, the session should be closed then.
+    pub fn check(
+        &mut self,
+        secret: Option<&Secret>,
+        item: ComItem,
+    ) -> Result<Option<ComItem>, Error> {
+        if secret.is_none() {
+            // the session never got a nonce it could answer after a `$secret`
+            self.authenticated = true;
+        }
+        match (item, secret) {
+            (ComItem::Auth(_), _) if self.is_authenticated(secret) => Ok(None),
+            (ComItem::Auth(mac), Some(secret)) => {
+                if secret.verify(self.nonce.as_bytes(), &mac) {
+                    self.authenticated = true;
+                    Ok(None)
+                } else {
+                    Err(Error::AuthFailed)
+                }
+            }
+            (item, _) if self.is_authenticated(secret) => Ok(Some(item)),
+            _ => Err(Error::NotAuthenticated),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser, Serialize};
+
+    fn item(slice: &[u8]) -> ComItem {
+        let mut deser = DeSer::<100>::from_slice(slice).unwrap();
+        ComItem::deserialize(&mut deser).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let secret = Secret::new(b"bench-7").unwrap();
+        let nonce = Nonce::new([0x5a; 16]);
+        let mac = secret.sign(nonce.as_bytes());
+        // the example of the protocol description
+        let mut ser = Ser::<80>::default();
+        mac.serialize(&mut ser).unwrap();
+        assert_eq!(
+            ser.as_slice(),
+            b",0c088bd4b7009630a704607f164d69a421178b6952ac12f89e81246015d6b673"
+        );
+
+        let mut handshake = Handshake::new(nonce);
+        assert!(matches!(handshake.challenge(), ComItem::Nonce(n) if n == nonce));
+        assert!(!handshake.is_authenticated(Some(&secret)));
+        assert_eq!(
+            handshake
+                .check(Some(&secret), item(b"$echo\n"))
+                .unwrap_err(),
+            Error::NotAuthenticated
+        );
+        let wrong = Secret::new(b"bench-8").unwrap().sign(nonce.as_bytes());
+        assert_eq!(
+            handshake
+                .check(Some(&secret), ComItem::Auth(wrong))
+                .unwrap_err(),
+            Error::AuthFailed
+        );
+        assert!(
+            handshake
+                .check(Some(&secret), ComItem::Auth(mac))
+                .unwrap()
+                .is_none()
+        );
+        assert!(handshake.is_authenticated(Some(&secret)));
+        assert!(matches!(
+            handshake.check(Some(&secret), item(b"$echo\n")),
+            Ok(Some(ComItem::Echo))
+        ));
+    }
+
+    #[test]
+    fn without_secret() {
+        let mut handshake = Handshake::new(Nonce::new([0; 16]));
+        assert!(handshake.is_authenticated(None));
+        assert!(matches!(
+            handshake.check(None, item(b"$save\n")),
+            Ok(Some(ComItem::Save))
+        ));
+    }
+
+    #[test]
+    fn secret_set_by_session() {
+        let mut handshake = Handshake::new(Nonce::new([0; 16]));
+        let secret = handshake.check(None, item(b"$secret,62656e63682d37\n"));
+        let Ok(Some(ComItem::Secret(Some(secret)))) = secret else {
+            panic!("no secret");
+        };
+        assert!(handshake.is_authenticated(Some(&secret)));
+        assert!(matches!(
+            handshake.check(Some(&secret), item(b"$save\n")),
+            Ok(Some(ComItem::Save))
+        ));
+    }
+}
@@ -0,0 +1,5 @@
+mod handshake;
+mod secret;
+
+pub use handshake::*;
+pub use secret::*;
@@ -0,0 +1,175 @@
+use crate::{DeSerialize, Error, Serialize};
+use core::fmt;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+pub const SECRET_MAX_LEN: usize = 32;
+pub const NONCE_LEN: usize = 16;
+pub const MAC_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Reads a field of hex bytes, the field must not be longer than the buffer
+fn get_hex(deser: &mut impl DeSerialize, buf: &mut [u8]) -> Result<usize, Error> {
+    fn get_nibble(b: u8) -> Result<u8, Error> {
+        match b {
+            b'0'..=b'9' => Ok(b - b'0'),
+            b'a'..=b'f' => Ok(b - b'a' + 10),
+            _ => Err(Error::ParseError),
+        }
+    }
+    let slice = &deser.get_slice()?[1..];
+    if slice.len() & 0x01 == 1 || slice.len() > 2 * buf.len() {
+        return Err(Error::ParseError);
+    }
+    for (byte, chunk) in buf.iter_mut().zip(slice.chunks(2)) {
+        *byte = get_nibble(chunk[0])? << 4 | get_nibble(chunk[1])?;
+    }
+    Ok(slice.len() / 2)
+}
+
+/// Key shared by the bridge and its hosts, 1 to 32 bytes
+#[derive(PartialEq, Copy, Clone)]
+pub struct Secret {
+    bytes: [u8; SECRET_MAX_LEN],
+    len: u8,
+}
+
+// the key never shows up in logs
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+impl Secret {
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        if key.is_empty() || key.len() > SECRET_MAX_LEN {
+            return Err(Error::ParseError);
+        }
+        let mut bytes = [0; SECRET_MAX_LEN];
+        bytes[..key.len()].copy_from_slice(key);
+        Ok(Self {
+            bytes,
+            len: key.len() as u8,
+        })
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// HMAC-SHA256 of the message
+    pub fn sign(&self, message: &[u8]) -> AuthMac {
+        let mut mac = HmacSha256::new_from_slice(self.key()).unwrap();
+        mac.update(message);
+        AuthMac(mac.finalize().into_bytes().into())
+    }
+
+    /// Compares the HMAC of the message in constant time
+    pub fn verify(&self, message: &[u8], auth_mac: &AuthMac) -> bool {
+        let mut mac = HmacSha256::new_from_slice(self.key()).unwrap();
+        mac.update(message);
+        mac.verify_slice(&auth_mac.0).is_ok()
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let mut key = [0; SECRET_MAX_LEN];
+        let len = get_hex(deser, &mut key)?;
+        Self::new(&key[..len])
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_slice_hex(self.key())
+    }
+}
+
+/// Random challenge sent by the bridge to a new session
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Nonce([u8; NONCE_LEN]);
+
+impl Nonce {
+    pub fn new(bytes: [u8; NONCE_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let mut bytes = [0; NONCE_LEN];
+        match get_hex(deser, &mut bytes)? {
+            NONCE_LEN => Ok(Self(bytes)),
+            _ => Err(Error::ParseError),
+        }
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_slice_hex(&self.0)
+    }
+}
+
+/// Answer of a host to the challenge, the HMAC of the nonce
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct AuthMac([u8; MAC_LEN]);
+
+impl AuthMac {
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let mut bytes = [0; MAC_LEN];
+        match get_hex(deser, &mut bytes)? {
+            MAC_LEN => Ok(Self(bytes)),
+            _ => Err(Error::ParseError),
+        }
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_slice_hex(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    #[test]
+    fn sign() {
+        // RFC 4231, test case 2
+        let secret = Secret::new(b"Jefe").unwrap();
+        let slice = b",5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843\n";
+        let mut deser = DeSer::<80>::from_slice(slice).unwrap();
+        let expected = AuthMac::deserialize(&mut deser).unwrap();
+        let message = b"what do ya want for nothing?";
+        assert_eq!(secret.sign(message), expected);
+        assert!(secret.verify(message, &expected));
+        assert!(!secret.verify(b"what do ya want for nothing!", &expected));
+        assert!(!Secret::new(b"jefe").unwrap().verify(message, &expected));
+    }
+
+    #[test]
+    fn secret_serialize() {
+        let slice = b",000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\n";
+        let mut deser = DeSer::<80>::from_slice(slice).unwrap();
+        let secret = Secret::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<80>::default();
+        secret.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        for slice in [
+            &b",\n"[..],
+            b",0a1\n",
+            b",0A\n",
+            b",000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20\n",
+        ] {
+            let mut deser = DeSer::<80>::from_slice(slice).unwrap();
+            assert_eq!(Secret::deserialize(&mut deser), Err(Error::ParseError));
+        }
+
+        let mut deser = DeSer::<80>::from_slice(b",00112233\n").unwrap();
+        assert_eq!(Nonce::deserialize(&mut deser), Err(Error::ParseError));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{CanFrame, DeSerialize, Error, Serialize, filter::TInstant};
+use embassy_time::Instant;
+use embedded_can::{Frame, Id};
+use heapless::Vec;
+
+/// Everything the bridge knows about one id
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct IdEntry {
+    frame: CanFrame,
+    count: u32,
+    period: u32,
+    last: TInstant,
+}
+
+impl IdEntry {
+    /// The last frame received with this id
+    pub fn frame(&self) -> &CanFrame {
+        &self.frame
+    }
+
+    /// The number of frames received with this id
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The time between the last two frames in milliseconds
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let frame = CanFrame::deserialize(deser)?;
+        let count = deser.get_u32()?;
+        let period = deser.get_u32()?;
+        Ok(Self {
+            frame,
+            count,
+            period,
+            last: 0.into(),
+        })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        self.frame.serialize(ser)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.count)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.period)?;
+        Ok(())
+    }
+}
+
+/// Table of all ids seen on the bus, independent of any filter
+pub struct IdTable<const CAP: usize> {
+    entries: Vec<IdEntry, CAP>,
+}
+
+impl<const CAP: usize> Default for IdTable<CAP> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<const CAP: usize> IdTable<CAP> {
+    pub fn update(&mut self, frame: &CanFrame, instant: Instant) -> Result<(), Error> {
+        let instant: TInstant = instant.into();
+        let id = frame.id();
+        for entry in &mut self.entries {
+            if entry.frame.id() == id {
+                entry.frame = *frame;
+                entry.count = entry.count.wrapping_add(1);
+                entry.period = entry.last.dist(instant);
+                entry.last = instant;
+                return Ok(());
+            }
+        }
+        self.entries
+            .push(IdEntry {
+                frame: *frame,
+                count: 1,
+                period: 0,
+                last: instant,
+            })
+            .map_err(|_| Error::BufIsFull)
+    }
+
+    /// Returns the entries of the standard and the extended id with the raw value `id`
+    pub fn find(&self, id: u32) -> impl Iterator<Item = &IdEntry> + '_ {
+        self.entries
+            .iter()
+            .filter(move |entry| match entry.frame.id() {
+                Id::Standard(sid) => sid.as_raw() as u32 == id,
+                Id::Extended(eid) => eid.as_raw() == id,
+            })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &IdEntry> + '_ {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_can::{ExtendedId, StandardId};
+
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn id_table() {
+        let mut table = IdTable::<3>::default();
+        let ext = CanFrame::new(ExtendedId::new(0x12a).unwrap(), &[]).unwrap();
+        table
+            .update(&s_frame(0x12a, &[1]), Instant::from_millis(100))
+            .unwrap();
+        table.update(&ext, Instant::from_millis(110)).unwrap();
+        table
+            .update(&s_frame(0x12a, &[2]), Instant::from_millis(200))
+            .unwrap();
+        table
+            .update(&s_frame(0x12b, &[]), Instant::from_millis(210))
+            .unwrap();
+        table
+            .update(&s_frame(0x12a, &[3]), Instant::from_millis(320))
+            .unwrap();
+        assert_eq!(
+            table.update(&s_frame(0x12c, &[]), Instant::from_millis(400)),
+            Err(Error::BufIsFull)
+        );
+        assert_eq!(table.len(), 3);
+
+        let entry = table.find(0x12a).next().unwrap();
+        assert_eq!(entry.frame(), &s_frame(0x12a, &[3]));
+        assert_eq!(entry.count(), 3);
+        assert_eq!(entry.period(), 120);
+        assert_eq!(table.find(0x12a).count(), 2);
+        assert_eq!(table.find(0x12c).count(), 0);
+
+        table.clear();
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn id_entry_serialize() {
+        let slice = b",12a,3,1a2b3c,17,100,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let entry = IdEntry::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<40>::default();
+        entry.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+    }
+}
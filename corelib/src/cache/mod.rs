@@ -0,0 +1,5 @@
+mod id_table;
+mod snapshot;
+
+pub use id_table::*;
+pub use snapshot::*;
@@ -0,0 +1,108 @@
+use crate::{CanFrame, Error};
+use embedded_can::Frame;
+use heapless::Vec;
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Entry {
+    frame: CanFrame,
+    changed: bool,
+}
+
+/// Last value cache for the snapshot mode
+///
+/// The cache keeps the latest frame of each id. Frames that differ from the
+/// cached value mark the id as changed until the next call of `take_changed`.
+pub struct Snapshot<const CAP: usize> {
+    entries: Vec<Entry, CAP>,
+}
+
+impl<const CAP: usize> Default for Snapshot<CAP> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<const CAP: usize> Snapshot<CAP> {
+    pub fn update(&mut self, frame: &CanFrame) -> Result<(), Error> {
+        let id = frame.id();
+        for entry in &mut self.entries {
+            if entry.frame.id() == id {
+                if entry.frame != *frame {
+                    entry.frame = *frame;
+                    entry.changed = true;
+                }
+                return Ok(());
+            }
+        }
+        self.entries
+            .push(Entry {
+                frame: *frame,
+                changed: true,
+            })
+            .map_err(|_| Error::BufIsFull)
+    }
+
+    /// Returns the frames of all changed ids and resets their changed flag
+    pub fn take_changed(&mut self) -> impl Iterator<Item = CanFrame> + '_ {
+        self.entries.iter_mut().filter_map(|entry| {
+            if entry.changed {
+                entry.changed = false;
+                Some(entry.frame)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_can::{ExtendedId, StandardId};
+
+    use super::*;
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut snapshot = Snapshot::<3>::default();
+        snapshot.update(&s_frame(0x12a, &[1, 2])).unwrap();
+        snapshot.update(&s_frame(0x12a, &[1, 3])).unwrap();
+        snapshot.update(&s_frame(0x12b, &[1])).unwrap();
+        let ext = CanFrame::new(ExtendedId::new(0x12a).unwrap(), &[]).unwrap();
+        snapshot.update(&ext).unwrap();
+        assert_eq!(snapshot.len(), 3);
+
+        let changed: Vec<CanFrame, 3> = snapshot.take_changed().collect();
+        assert_eq!(
+            changed,
+            [s_frame(0x12a, &[1, 3]), s_frame(0x12b, &[1]), ext]
+        );
+        assert_eq!(snapshot.take_changed().count(), 0);
+
+        snapshot.update(&s_frame(0x12a, &[1, 3])).unwrap();
+        snapshot.update(&s_frame(0x12b, &[2])).unwrap();
+        let changed: Vec<CanFrame, 3> = snapshot.take_changed().collect();
+        assert_eq!(changed, [s_frame(0x12b, &[2])]);
+
+        assert_eq!(snapshot.update(&s_frame(0x12c, &[])), Err(Error::BufIsFull));
+        snapshot.clear();
+        assert!(snapshot.is_empty());
+    }
+}
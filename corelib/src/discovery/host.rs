@@ -0,0 +1,84 @@
+extern crate std;
+
+use crate::{BridgeInfo, ComItem, DATAGRAM_LEN, DISCOVERY_PORT, Serialize};
+use std::{
+    io::{
+        self,
+        ErrorKind::{TimedOut, WouldBlock},
+    },
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    time::{Duration, Instant},
+    vec::Vec,
+};
+
+/// Finds the bridges in the local network with a broadcast query
+///
+/// The answers are collected until the timeout, every bridge is listed once.
+pub fn discover(timeout: Duration) -> io::Result<Vec<BridgeInfo>> {
+    let broadcast = SocketAddrV4::new(Ipv4Addr::BROADCAST, DISCOVERY_PORT);
+    discover_at(broadcast.into(), timeout)
+}
+
+/// Sends the discovery query to the given address, e.g. a directed broadcast address
+pub fn discover_at(addr: SocketAddr, timeout: Duration) -> io::Result<Vec<BridgeInfo>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(ComItem::Discover.serialize().as_slice(), addr)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut bridges = Vec::new();
+    let mut buf = [0; DATAGRAM_LEN];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let n = match socket.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), WouldBlock | TimedOut) => break,
+            Err(e) => return Err(e),
+        };
+        // other datagrams on the port are ignored
+        if let Ok(info) = BridgeInfo::from_answer(&buf[..n])
+            && !bridges.contains(&info)
+        {
+            bridges.push(info);
+        }
+    }
+    Ok(bridges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Endpoint;
+    use std::thread;
+
+    #[test]
+    fn discover_local() {
+        let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = responder.local_addr().unwrap();
+        let info = BridgeInfo::new(
+            "espcand-a0b1c2d3e4f5",
+            Endpoint::new([127, 0, 0, 1], 1234),
+            "0.1.0",
+            250_000,
+        )
+        .unwrap();
+        let answer = ComItem::BridgeInfo(info.clone()).serialize();
+        let handle = thread::spawn(move || {
+            let mut buf = [0; DATAGRAM_LEN];
+            let (n, host) = responder.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..n], b"$discover\n");
+            // a bridge answering twice is listed once
+            for _ in 0..2 {
+                responder.send_to(answer.as_slice(), host).unwrap();
+            }
+        });
+
+        let bridges = discover_at(addr, Duration::from_millis(300)).unwrap();
+        handle.join().unwrap();
+        assert_eq!(bridges, [info]);
+    }
+}
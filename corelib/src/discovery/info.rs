@@ -0,0 +1,135 @@
+use crate::{ComItem, DATAGRAM_LEN, DeSer, DeSerialize, Endpoint, Error, Serialize};
+use core::fmt::Write;
+use heapless::String;
+
+/// UDP port answering discovery queries, the same number as the TCP port of the sessions
+pub const DISCOVERY_PORT: u16 = 1234;
+pub const NAME_LEN: usize = 24;
+pub const VERSION_LEN: usize = 16;
+
+/// Name of the bridge derived from its MAC address, e.g. `espcand-a0b1c2d3e4f5`
+pub fn bridge_name(mac: &[u8; 6]) -> String<NAME_LEN> {
+    let mut name = String::new();
+    // 20 characters always fit
+    let _ = name.push_str("espcand-");
+    for byte in mac {
+        let _ = write!(name, "{byte:02x}");
+    }
+    name
+}
+
+/// Text without separators, printable ASCII only
+fn text<const N: usize>(slice: &[u8]) -> Result<String<N>, Error> {
+    if slice.is_empty() || !slice.iter().all(u8::is_ascii_graphic) || slice.contains(&b',') {
+        return Err(Error::ParseError);
+    }
+    let mut text = String::new();
+    for b in slice {
+        text.push(*b as char).map_err(|_| Error::ParseError)?;
+    }
+    Ok(text)
+}
+
+/// Answer of a bridge to a discovery query
+#[derive(PartialEq, Debug, Clone)]
+pub struct BridgeInfo {
+    name: String<NAME_LEN>,
+    endpoint: Endpoint,
+    version: String<VERSION_LEN>,
+    bitrate: u32,
+}
+
+impl BridgeInfo {
+    pub fn new(name: &str, endpoint: Endpoint, version: &str, bitrate: u32) -> Result<Self, Error> {
+        Ok(Self {
+            name: text(name.as_bytes())?,
+            endpoint,
+            version: text(version.as_bytes())?,
+            bitrate,
+        })
+    }
+
+    /// Reads the answer received via UDP
+    pub fn from_answer(packet: &[u8]) -> Result<Self, Error> {
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(packet)?;
+        match ComItem::deserialize(&mut deser)? {
+            ComItem::BridgeInfo(info) => Ok(info),
+            _ => Err(Error::ParseError),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Address and TCP port of the sessions
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+
+    /// Firmware version
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Bitrate of the CAN bus in bit/s
+    pub fn bitrate(&self) -> u32 {
+        self.bitrate
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let name = text(&deser.get_slice()?[1..])?;
+        let endpoint = Endpoint::deserialize(deser)?;
+        let version = text(&deser.get_slice()?[1..])?;
+        let bitrate = deser.get_u32()?;
+        Ok(Self {
+            name,
+            endpoint,
+            version,
+            bitrate,
+        })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_slice(self.name.as_bytes())?;
+        self.endpoint.serialize(ser)?;
+        ser.add_byte(b',')?;
+        ser.add_slice(self.version.as_bytes())?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.bitrate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge_info() {
+        let name = bridge_name(&[0xa0, 0xb1, 0xc2, 0xd3, 0xe4, 0xf5]);
+        assert_eq!(name.as_str(), "espcand-a0b1c2d3e4f5");
+        let endpoint = Endpoint::new([192, 168, 1, 20], 1234);
+        let info = BridgeInfo::new(&name, endpoint, "0.1.0", 500_000).unwrap();
+
+        let ser = ComItem::BridgeInfo(info.clone()).serialize();
+        assert_eq!(
+            ser.as_slice(),
+            b"$bridge,espcand-a0b1c2d3e4f5,192.168.1.20,1234,0.1.0,500000\n"
+        );
+        assert_eq!(BridgeInfo::from_answer(ser.as_slice()), Ok(info));
+
+        assert_eq!(
+            BridgeInfo::new("two words", endpoint, "0.1.0", 0),
+            Err(Error::ParseError)
+        );
+        assert_eq!(
+            BridgeInfo::from_answer(b"$discover\n"),
+            Err(Error::ParseError)
+        );
+        assert_eq!(
+            BridgeInfo::from_answer(b"$bridge,,192.168.1.20,1234,0.1.0,500000\n"),
+            Err(Error::ParseError)
+        );
+    }
+}
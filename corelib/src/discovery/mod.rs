@@ -0,0 +1,7 @@
+#[cfg(feature = "std")]
+mod host;
+mod info;
+
+#[cfg(feature = "std")]
+pub use host::*;
+pub use info::*;
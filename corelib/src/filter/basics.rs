@@ -1,15 +1,69 @@
-use super::{IdTimes, TInstant, add_ones_zeros, check, get_ones_zeros};
-use crate::{DeSerialize, Error, Serialize};
+use super::{
+    FramePredicate, IdTimes, TInstant, Throttle, add_ones_zeros, check, get_ones_zeros, raw_id,
+};
+use crate::{CanFrame, DeSerialize, Error, Serialize};
 use embassy_time::Instant;
-use embedded_can::Id;
+use embedded_can::Frame;
 use heapless::Vec;
 
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct FilterStats {
+    hits: u32,
+    forwards: u32,
+    throttled: u32,
+}
+
+impl FilterStats {
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn forwards(&self) -> u32 {
+        self.forwards
+    }
+
+    pub fn throttled(&self) -> u32 {
+        self.throttled
+    }
+
+    fn hit(&mut self) {
+        self.hits = self.hits.wrapping_add(1);
+    }
+
+    fn forward(&mut self) {
+        self.forwards = self.forwards.wrapping_add(1);
+    }
+
+    fn throttle(&mut self) {
+        self.throttled = self.throttled.wrapping_add(1);
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        Ok(Self {
+            hits: deser.get_u32()?,
+            forwards: deser.get_u32()?,
+            throttled: deser.get_u32()?,
+        })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint(self.hits)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.forwards)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.throttled)?;
+        Ok(())
+    }
+}
+
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct PrePFilter {
     extended: bool,
-    duration: u32,
+    throttle: Throttle,
     ones: u32,
     zeros: u32,
+    predicate: FramePredicate,
 }
 
 impl PrePFilter {
@@ -17,50 +71,67 @@ impl PrePFilter {
         let (extended, ones, zeros) = get_ones_zeros(bytes)?;
         Ok(Self {
             extended,
-            duration,
+            throttle: Throttle::Duration(duration),
             ones,
             zeros,
+            predicate: FramePredicate::default(),
         })
     }
 
+    pub fn with_predicate(mut self, predicate: FramePredicate) -> Self {
+        self.predicate = predicate;
+        self
+    }
+
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
     pub fn into(self) -> PFilter {
         PFilter {
             extended: self.extended,
-            duration: self.duration,
+            throttle: self.throttle,
             ones: self.ones,
             zeros: self.zeros,
+            predicate: self.predicate,
             id_times: IdTimes::new(),
+            stats: FilterStats::default(),
         }
     }
 
     pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
-        let duration = deser.get_u32()?;
+        let throttle = Throttle::deserialize(deser)?;
         let slice = &deser.get_slice()?[1..];
         let (extended, ones, zeros) = get_ones_zeros(slice)?;
+        let predicate = FramePredicate::deserialize(deser)?;
         Ok(Self {
             extended,
-            duration,
+            throttle,
             ones,
             zeros,
+            predicate,
         })
     }
 
     pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
         ser.add_byte(b',')?;
-        ser.add_uint(self.duration)?;
+        self.throttle.serialize(ser)?;
         ser.add_byte(b',')?;
         add_ones_zeros(ser, self.extended, self.ones, self.zeros);
-        Ok(())
+        self.predicate.serialize(ser)
     }
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct PFilter {
     extended: bool,
-    duration: u32,
+    throttle: Throttle,
     ones: u32,
     zeros: u32,
+    predicate: FramePredicate,
     id_times: IdTimes<16>,
+    stats: FilterStats,
 }
 
 impl PFilter {
@@ -68,46 +139,61 @@ impl PFilter {
         let (extended, ones, zeros) = get_ones_zeros(bytes)?;
         Ok(Self {
             extended,
-            duration,
+            throttle: Throttle::Duration(duration),
             ones,
             zeros,
+            predicate: FramePredicate::default(),
             id_times: IdTimes::new(),
+            stats: FilterStats::default(),
         })
     }
 
+    pub fn with_predicate(mut self, predicate: FramePredicate) -> Self {
+        self.predicate = predicate;
+        self
+    }
+
     pub fn as_pre_pfilter(&self) -> PrePFilter {
         PrePFilter {
             extended: self.extended,
-            duration: self.duration,
+            throttle: self.throttle,
             ones: self.ones,
             zeros: self.zeros,
+            predicate: self.predicate,
         }
     }
 
-    pub fn check(&mut self, id: Id, instant: TInstant) -> bool {
-        let id = match id {
-            Id::Extended(id) => {
-                if self.extended {
-                    id.as_raw()
-                } else {
-                    return false;
-                }
-            }
-            Id::Standard(id) => {
-                if self.extended {
-                    return false;
-                } else {
-                    id.as_raw() as u32
-                }
-            }
+    pub fn check(&mut self, frame: &CanFrame, instant: TInstant) -> bool {
+        let id = match raw_id(frame.id(), self.extended) {
+            Some(id) => id,
+            None => return false,
+        };
+        if !check(id, self.ones, self.zeros, self.extended) || !self.predicate.check(frame) {
+            return false;
+        }
+        self.stats.hit();
+        let pass = match self.throttle {
+            Throttle::Duration(duration) => self.id_times.check_instant(id, instant, duration),
+            Throttle::Decimate(every) => self.id_times.check_count(id, every),
         };
-        if !self.id_times.check_instant(id, instant, self.duration) {
+        if !pass {
+            self.stats.throttle();
             return false;
         }
-        check(id, self.ones, self.zeros, self.extended)
+        self.stats.forward();
+        true
+    }
+
+    pub fn stats(&self) -> FilterStats {
+        self.stats
+    }
+
+    pub fn clear_stats(&mut self) {
+        self.stats = FilterStats::default();
     }
 }
 
+#[derive(Clone)]
 pub struct PFilters<const CAP: usize> {
     pfilters: Vec<PFilter, CAP>,
 }
@@ -127,13 +213,13 @@ impl<const CAP: usize> PFilters<CAP> {
             .map_err(|_| Error::BufIsFull)
     }
 
-    pub fn check(&mut self, id: Id, instant: Instant) -> bool {
+    pub fn check(&mut self, frame: &CanFrame, instant: Instant) -> bool {
         let instant = instant.into();
         if self.pfilters.is_empty() {
             return true;
         } else {
             for pfilter in &mut self.pfilters {
-                if pfilter.check(id, instant) {
+                if pfilter.check(frame, instant) {
                     return true;
                 }
             }
@@ -145,6 +231,12 @@ impl<const CAP: usize> PFilters<CAP> {
         self.pfilters.clear();
     }
 
+    pub fn clear_stats(&mut self) {
+        for pfilter in &mut self.pfilters {
+            pfilter.clear_stats();
+        }
+    }
+
     pub fn get_vec_ref(&self) -> &Vec<PFilter, CAP> {
         &self.pfilters
     }
@@ -155,6 +247,8 @@ pub struct NFilter {
     extended: bool,
     ones: u32,
     zeros: u32,
+    predicate: FramePredicate,
+    stats: FilterStats,
 }
 
 impl NFilter {
@@ -164,46 +258,63 @@ impl NFilter {
             extended,
             ones,
             zeros,
+            predicate: FramePredicate::default(),
+            stats: FilterStats::default(),
         })
     }
 
-    pub fn check(&mut self, id: Id) -> bool {
-        let id = match id {
-            Id::Extended(id) => {
-                if self.extended {
-                    id.as_raw()
-                } else {
-                    return false;
-                }
-            }
-            Id::Standard(id) => {
-                if self.extended {
-                    return false;
-                } else {
-                    id.as_raw() as u32
-                }
+    pub fn with_predicate(mut self, predicate: FramePredicate) -> Self {
+        self.predicate = predicate;
+        self
+    }
+
+    pub fn check(&mut self, frame: &CanFrame) -> bool {
+        if !self.matches(frame) {
+            return false;
+        }
+        self.stats.hit();
+        true
+    }
+
+    /// Checks a frame without counting it in the statistics
+    pub fn matches(&self, frame: &CanFrame) -> bool {
+        match raw_id(frame.id(), self.extended) {
+            Some(id) => {
+                check(id, self.ones, self.zeros, self.extended) && self.predicate.check(frame)
             }
-        };
-        check(id, self.ones, self.zeros, self.extended)
+            None => false,
+        }
+    }
+
+    pub fn stats(&self) -> FilterStats {
+        self.stats
+    }
+
+    pub fn clear_stats(&mut self) {
+        self.stats = FilterStats::default();
     }
 
     pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
         let slice = &deser.get_slice()?[1..];
         let (extended, ones, zeros) = get_ones_zeros(slice)?;
+        let predicate = FramePredicate::deserialize(deser)?;
         Ok(Self {
             extended,
             ones,
             zeros,
+            predicate,
+            stats: FilterStats::default(),
         })
     }
 
     pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
         ser.add_byte(b',')?;
         add_ones_zeros(ser, self.extended, self.ones, self.zeros);
-        Ok(())
+        self.predicate.serialize(ser)
     }
 }
 
+#[derive(Clone)]
 pub struct NFilters<const CAP: usize> {
     nfilters: Vec<NFilter, CAP>,
 }
@@ -221,12 +332,12 @@ impl<const CAP: usize> NFilters<CAP> {
         self.nfilters.push(nfilter).map_err(|_| Error::BufIsFull)
     }
 
-    pub fn check(&mut self, id: Id) -> bool {
+    pub fn check(&mut self, frame: &CanFrame) -> bool {
         if self.nfilters.is_empty() {
             return false;
         } else {
             for nfilter in &mut self.nfilters {
-                if nfilter.check(id) {
+                if nfilter.check(frame) {
                     return true;
                 }
             }
@@ -238,12 +349,19 @@ impl<const CAP: usize> NFilters<CAP> {
         self.nfilters.clear();
     }
 
+    pub fn clear_stats(&mut self) {
+        for nfilter in &mut self.nfilters {
+            nfilter.clear_stats();
+        }
+    }
+
     pub fn get_vec_ref(&self) -> &Vec<NFilter, CAP> {
         &self.nfilters
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use embedded_can::{ExtendedId, StandardId};
 
@@ -252,12 +370,12 @@ mod tests {
     extern crate std;
     use std::println;
 
-    fn s_id(id: u32) -> Id {
-        Id::Standard(StandardId::new(id as u16).unwrap())
+    fn s_frame(id: u32) -> CanFrame {
+        CanFrame::new(StandardId::new(id as u16).unwrap(), &[]).unwrap()
     }
 
-    fn e_id(id: u32) -> Id {
-        Id::Extended(ExtendedId::new(id).unwrap())
+    fn e_frame(id: u32) -> CanFrame {
+        CanFrame::new(ExtendedId::new(id).unwrap(), &[]).unwrap()
     }
 
     #[test]
@@ -269,20 +387,24 @@ mod tests {
             PFilter::new(0, b"110_0110_0011"),
             Ok(PFilter {
                 extended: false,
-                duration: 0,
+                throttle: Throttle::Duration(0),
                 ones: 0b110_0110_0011,
                 zeros: 0b1_1001_1100,
+                predicate: FramePredicate::default(),
                 id_times: IdTimes::new(),
+                stats: FilterStats::default(),
             })
         );
         assert_eq!(
             PFilter::new(0, b"1*0_0110_0**1"),
             Ok(PFilter {
                 extended: false,
-                duration: 0,
+                throttle: Throttle::Duration(0),
                 ones: 0b100_0110_0001,
                 zeros: 0b1_1001_1000,
+                predicate: FramePredicate::default(),
                 id_times: IdTimes::new(),
+                stats: FilterStats::default(),
             })
         );
         assert_eq!(PFilter::new(0, b"1*0_0110_0**1_*"), Err(Error::ParseError));
@@ -290,10 +412,12 @@ mod tests {
             PFilter::new(123, b"1_0000_1111_0000_1111_0000_1111_0000"),
             Ok(PFilter {
                 extended: true,
-                duration: 123,
+                throttle: Throttle::Duration(123),
                 ones: 0b1_0000_1111_0000_1111_0000_1111_0000,
                 zeros: 0b1111_0000_1111_0000_1111_0000_1111,
+                predicate: FramePredicate::default(),
                 id_times: IdTimes::new(),
+                stats: FilterStats::default(),
             })
         );
     }
@@ -309,6 +433,8 @@ mod tests {
                 extended: false,
                 ones: 0b110_0110_0011,
                 zeros: 0b1_1001_1100,
+                predicate: FramePredicate::default(),
+                stats: FilterStats::default(),
             })
         );
         assert_eq!(
@@ -317,6 +443,8 @@ mod tests {
                 extended: false,
                 ones: 0b100_0110_0001,
                 zeros: 0b1_1001_1000,
+                predicate: FramePredicate::default(),
+                stats: FilterStats::default(),
             })
         );
         assert_eq!(NFilter::new(b"1*0_0110_0**1_*"), Err(Error::ParseError));
@@ -326,6 +454,8 @@ mod tests {
                 extended: true,
                 ones: 0b1_0000_1111_0000_1111_0000_1111_0000,
                 zeros: 0b1111_0000_1111_0000_1111_0000_1111,
+                predicate: FramePredicate::default(),
+                stats: FilterStats::default(),
             })
         );
     }
@@ -333,27 +463,45 @@ mod tests {
     #[test]
     fn check_pfilter() {
         let mut filter = PFilter::new(0, b"1*0_0110_0**1").unwrap();
-        assert_eq!(filter.check(s_id(0b110_0110_0111), 0.into()), true);
-        assert_eq!(filter.check(s_id(0b100_0110_0001), 0.into()), true);
-        assert_eq!(filter.check(s_id(0b110_0110_0110), 0.into()), false);
-        assert_eq!(filter.check(s_id(0b110_0110_1111), 0.into()), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 0.into()), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 0.into()), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0110), 0.into()), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_1111), 0.into()), false);
 
         let mut filter = PFilter::new(1000, b"1*0_0110_0**1").unwrap();
-        assert_eq!(filter.check(s_id(0b110_0110_0111), 500.into()), true);
-        assert_eq!(filter.check(s_id(0b110_0110_0111), 1000.into()), false);
-        assert_eq!(filter.check(s_id(0b110_0110_0111), 1501.into()), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 500.into()), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 1000.into()), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 1501.into()), true);
 
-        assert_eq!(filter.check(s_id(0b100_0110_0001), 500.into()), true);
-        assert_eq!(filter.check(s_id(0b100_0110_0001), 1000.into()), false);
-        assert_eq!(filter.check(s_id(0b100_0110_0001), 1501.into()), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 500.into()), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 1000.into()), false);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 1501.into()), true);
 
         let mut filter = PFilter::new(0, b"1_0000_1111_0000_1111_0000_1111_0000").unwrap();
         assert_eq!(
-            filter.check(e_id(0b1_0000_1111_0000_1111_0000_1111_0000), 0.into()),
+            filter.check(&e_frame(0b1_0000_1111_0000_1111_0000_1111_0000), 0.into()),
             true
         );
     }
 
+    #[test]
+    fn check_pfilter_decimate() {
+        let mut filter = PFilter::new(0, b"1*0_0110_0**1").unwrap();
+        filter.throttle = Throttle::Decimate(3);
+        let results: Vec<bool, 7> = (0..7)
+            .map(|_| filter.check(&s_frame(0b110_0110_0111), 0.into()))
+            .collect();
+        assert_eq!(results, [true, false, false, true, false, false, true]);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 0.into()), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 0.into()), false);
+        assert_eq!(filter.stats().forwards(), 4);
+        assert_eq!(filter.stats().throttled(), 5);
+
+        filter.throttle = Throttle::Decimate(1);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 0.into()), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001), 0.into()), true);
+    }
+
     #[test]
     fn check_pfilters() {
         let mut pfilters = PFilters::<10>::default();
@@ -362,15 +510,15 @@ mod tests {
         let filter = PrePFilter::new(0, b"110_0110_0001").unwrap();
         pfilters.add(filter).unwrap();
         assert_eq!(
-            pfilters.check(s_id(0b110_0110_0000), Instant::from_millis(0)),
+            pfilters.check(&s_frame(0b110_0110_0000), Instant::from_millis(0)),
             true
         );
         assert_eq!(
-            pfilters.check(s_id(0b110_0110_0001), Instant::from_millis(0)),
+            pfilters.check(&s_frame(0b110_0110_0001), Instant::from_millis(0)),
             true
         );
         assert_eq!(
-            pfilters.check(s_id(0b110_0110_0011), Instant::from_millis(0)),
+            pfilters.check(&s_frame(0b110_0110_0011), Instant::from_millis(0)),
             false
         );
     }
@@ -378,20 +526,20 @@ mod tests {
     #[test]
     fn check_nfilter() {
         let mut filter = NFilter::new(b"1*0_0110_0**1").unwrap();
-        assert_eq!(filter.check(s_id(0b110_0110_0111)), true);
-        assert_eq!(filter.check(s_id(0b100_0110_0001)), true);
-        assert_eq!(filter.check(s_id(0b110_0110_0110)), false);
-        assert_eq!(filter.check(s_id(0b110_0110_1111)), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111)), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001)), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0110)), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_1111)), false);
 
         let mut filter = NFilter::new(b"1*0_0110_0**1").unwrap();
-        assert_eq!(filter.check(s_id(0b110_0110_0111)), true);
-        assert_eq!(filter.check(s_id(0b100_0110_0001)), true);
-        assert_eq!(filter.check(s_id(0b110_0110_0111)), true);
-        assert_eq!(filter.check(s_id(0b100_0110_0001)), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111)), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001)), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111)), true);
+        assert_eq!(filter.check(&s_frame(0b100_0110_0001)), true);
 
         let mut filter = NFilter::new(b"1_0000_1111_0000_1111_0000_1111_0000").unwrap();
         assert_eq!(
-            filter.check(e_id(0b1_0000_1111_0000_1111_0000_1111_0000)),
+            filter.check(&e_frame(0b1_0000_1111_0000_1111_0000_1111_0000)),
             true
         );
     }
@@ -403,9 +551,101 @@ mod tests {
         nfilters.add(filter).unwrap();
         let filter = NFilter::new(b"110_0110_0001").unwrap();
         nfilters.add(filter).unwrap();
-        assert_eq!(nfilters.check(s_id(0b110_0110_0000)), true);
-        assert_eq!(nfilters.check(s_id(0b110_0110_0001)), true);
-        assert_eq!(nfilters.check(s_id(0b110_0110_0011)), false);
+        assert_eq!(nfilters.check(&s_frame(0b110_0110_0000)), true);
+        assert_eq!(nfilters.check(&s_frame(0b110_0110_0001)), true);
+        assert_eq!(nfilters.check(&s_frame(0b110_0110_0011)), false);
+    }
+
+    #[test]
+    fn filter_stats() {
+        let mut filter = PFilter::new(1000, b"1*0_0110_0**1").unwrap();
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 500.into()), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 1000.into()), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0110), 1000.into()), false);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111), 1501.into()), true);
+        let stats = filter.stats();
+        assert_eq!(stats.hits(), 3);
+        assert_eq!(stats.forwards(), 2);
+        assert_eq!(stats.throttled(), 1);
+        filter.clear_stats();
+        assert_eq!(filter.stats(), FilterStats::default());
+
+        let mut filter = NFilter::new(b"1*0_0110_0**1").unwrap();
+        assert_eq!(filter.check(&s_frame(0b110_0110_0111)), true);
+        assert_eq!(filter.check(&s_frame(0b110_0110_0110)), false);
+        assert_eq!(filter.stats().hits(), 1);
+
+        let slice = b",17,4,13,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let stats = FilterStats::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<40>::default();
+        stats.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+    }
+
+    #[test]
+    fn check_predicate() {
+        let remote = CanFrame::new_remote(StandardId::new(0x12a).unwrap(), 4).unwrap();
+        let empty = s_frame(0x12a);
+        let data = CanFrame::new(StandardId::new(0x12a).unwrap(), &[1, 2, 3, 4]).unwrap();
+
+        let mut filter = NFilter::new(b"***_****_****")
+            .unwrap()
+            .with_predicate(FramePredicate::new(Some(true), None).unwrap());
+        assert_eq!(filter.check(&remote), true);
+        assert_eq!(filter.check(&empty), false);
+        assert_eq!(filter.check(&data), false);
+
+        let mut filter = NFilter::new(b"***_****_****")
+            .unwrap()
+            .with_predicate(FramePredicate::new(Some(false), Some(0)).unwrap());
+        assert_eq!(filter.check(&remote), false);
+        assert_eq!(filter.check(&empty), true);
+        assert_eq!(filter.check(&data), false);
+
+        let mut filter = PFilter::new(0, b"***_****_****")
+            .unwrap()
+            .with_predicate(FramePredicate::new(None, Some(4)).unwrap());
+        assert_eq!(filter.check(&remote, 0.into()), true);
+        assert_eq!(filter.check(&empty, 0.into()), false);
+        assert_eq!(filter.check(&data, 0.into()), true);
+
+        assert_eq!(FramePredicate::new(None, Some(9)), Err(Error::ParseError));
+    }
+
+    #[test]
+    fn predicate_serialize() {
+        for slice in [
+            &b",111_1111_0000,d,"[..],
+            &b",111_1111_0000,r,4,"[..],
+            &b",111_1111_0000,*,0,"[..],
+        ] {
+            let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+            let nfilter = NFilter::deserialize(&mut deser).unwrap();
+            let mut ser = Ser::<40>::default();
+            nfilter.serialize(&mut ser).unwrap();
+            assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+        }
+
+        let slice = b",0,111_1111_0000,r,4\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let pre_pfilter = PrePFilter::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<40>::default();
+        pre_pfilter.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",111_1111_0000,x\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(NFilter::deserialize(&mut deser), Err(Error::ParseError));
+
+        for slice in [&b",/0,111_1111_0000\n"[..], &b",/,111_1111_0000\n"[..]] {
+            let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+            assert_eq!(PrePFilter::deserialize(&mut deser), Err(Error::ParseError));
+        }
+
+        let slice = b",111_1111_0000,d,9\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(NFilter::deserialize(&mut deser), Err(Error::ParseError));
     }
 
     #[test]
@@ -436,5 +676,18 @@ mod tests {
         pre_pfilter.serialize(&mut ser).unwrap();
         println!("pre_pfilter {}", str::from_utf8(ser.as_slice()).unwrap());
         assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",/10,111_1010_1111,";
+        let mut deser = DeSer::<50>::from_slice(slice).unwrap();
+        let pre_pfilter = PrePFilter::deserialize(&mut deser).unwrap();
+        assert_eq!(
+            pre_pfilter,
+            PrePFilter::new(0, b"111_1010_1111")
+                .unwrap()
+                .with_throttle(Throttle::Decimate(10))
+        );
+        let mut ser = Ser::<40>::default();
+        pre_pfilter.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
     }
 }
@@ -1,5 +1,9 @@
 mod basics;
+mod set;
 mod utils;
 
 pub use basics::*;
+pub use set::FilterSet;
+pub use utils::{FramePredicate, IdPattern, Throttle};
+pub(crate) use utils::TInstant;
 use utils::*;
@@ -0,0 +1,78 @@
+use crate::{CanFrame, Error, NFilter, NFilters, PFilters, PrePFilter};
+use embassy_time::Instant;
+
+/// Positive and negative filters deciding together which frames are forwarded
+///
+/// A frame is forwarded if no negative filter and any positive filter matches.
+/// Without positive filters all frames not rejected by a negative filter are forwarded.
+#[derive(Clone, Default)]
+pub struct FilterSet<const CAP: usize> {
+    pfilters: PFilters<CAP>,
+    nfilters: NFilters<CAP>,
+}
+
+impl<const CAP: usize> FilterSet<CAP> {
+    pub fn add_pfilter(&mut self, pfilter: PrePFilter) -> Result<(), Error> {
+        self.pfilters.add(pfilter)
+    }
+
+    pub fn add_nfilter(&mut self, nfilter: NFilter) -> Result<(), Error> {
+        self.nfilters.add(nfilter)
+    }
+
+    pub fn check(&mut self, frame: &CanFrame, instant: Instant) -> bool {
+        !self.nfilters.check(frame) && self.pfilters.check(frame, instant)
+    }
+
+    pub fn clear(&mut self) {
+        self.pfilters.clear();
+        self.nfilters.clear();
+    }
+
+    pub fn clear_stats(&mut self) {
+        self.pfilters.clear_stats();
+        self.nfilters.clear_stats();
+    }
+
+    pub fn pfilters(&self) -> &PFilters<CAP> {
+        &self.pfilters
+    }
+
+    pub fn nfilters(&self) -> &NFilters<CAP> {
+        &self.nfilters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_can::{Frame, StandardId};
+
+    fn s_frame(id: u16) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), &[]).unwrap()
+    }
+
+    #[test]
+    fn filter_set() {
+        let now = Instant::from_millis(0);
+        let mut global = FilterSet::<2>::default();
+        global
+            .add_pfilter(PrePFilter::new(0, b"001_****_****").unwrap())
+            .unwrap();
+        global
+            .add_nfilter(NFilter::new(b"001_0000_0000").unwrap())
+            .unwrap();
+        assert!(global.check(&s_frame(0x101), now));
+        assert!(!global.check(&s_frame(0x100), now));
+        assert!(!global.check(&s_frame(0x201), now));
+
+        // a copy starts with the same filters and is changed independently
+        let mut session = global.clone();
+        session.clear_stats();
+        assert_eq!(session.pfilters().get_vec_ref()[0].stats().hits(), 0);
+        session.clear();
+        assert!(session.check(&s_frame(0x201), now));
+        assert!(!global.check(&s_frame(0x201), now));
+        assert_eq!(global.pfilters().get_vec_ref()[0].stats().hits(), 1);
+    }
+}
@@ -1,11 +1,12 @@
-use crate::{Error, Serialize};
+use crate::{CanFrame, DeSerialize, Error, Serialize};
 use embassy_time::Instant;
+use embedded_can::{Frame, Id};
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct TInstant(u32);
 
 impl TInstant {
-    fn dist(&self, other: TInstant) -> u32 {
+    pub(crate) fn dist(&self, other: TInstant) -> u32 {
         let d1 = self.0.wrapping_sub(other.0);
         let d2 = other.0.wrapping_sub(self.0);
         if d1 < d2 { d1 } else { d2 }
@@ -28,6 +29,7 @@ impl From<i32> for TInstant {
 struct IdTime {
     pub id: u32,
     pub instant: TInstant,
+    pub count: u32,
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -40,6 +42,7 @@ impl<const CAP: usize> IdTimes<CAP> {
         let id_time = IdTime {
             id: u32::MAX,
             instant: TInstant(0),
+            count: 0,
         };
         Self {
             id_times: [id_time; CAP],
@@ -66,6 +69,164 @@ impl<const CAP: usize> IdTimes<CAP> {
         }
         false // silently ignore ids, when id-buffer is fullcl
     }
+
+    pub fn check_count(&mut self, id: u32, every: u32) -> bool {
+        if every <= 1 {
+            return true;
+        }
+        for id_time in &mut self.id_times {
+            if id_time.id == u32::MAX {
+                id_time.id = id;
+                id_time.count = 1;
+                return true;
+            } else if id_time.id == id {
+                let forward = id_time.count == 0;
+                id_time.count = (id_time.count + 1) % every;
+                return forward;
+            }
+        }
+        false // silently ignore ids, when id-buffer is full
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Throttle {
+    /// Minimum interval between datagrams of the same id in milliseconds
+    Duration(u32),
+    /// Forward only every nth datagram of the same id
+    Decimate(u32),
+}
+
+impl Throttle {
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let decimate = deser.skip_prefix(b'/');
+        let r = deser.get_u32()?;
+        match decimate {
+            true if r == 0 => Err(Error::ParseError),
+            true => Ok(Self::Decimate(r)),
+            false => Ok(Self::Duration(r)),
+        }
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        match self {
+            Self::Duration(duration) => ser.add_uint(*duration),
+            Self::Decimate(every) => {
+                ser.add_byte(b'/')?;
+                ser.add_uint(*every)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct FramePredicate {
+    remote: Option<bool>,
+    dlc: Option<u8>,
+}
+
+impl FramePredicate {
+    pub fn new(remote: Option<bool>, dlc: Option<u8>) -> Result<Self, Error> {
+        match dlc {
+            Some(dlc) if dlc > 8 => Err(Error::ParseError),
+            _ => Ok(Self { remote, dlc }),
+        }
+    }
+
+    pub fn check(&self, frame: &CanFrame) -> bool {
+        let remote_ok = match self.remote {
+            Some(remote) => remote == frame.is_remote_frame(),
+            None => true,
+        };
+        let dlc_ok = match self.dlc {
+            Some(dlc) => dlc as usize == frame.dlc(),
+            None => true,
+        };
+        remote_ok && dlc_ok
+    }
+
+    /// Reads the optional frame type and dlc fields behind the match pattern
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        if !deser.has_next() {
+            return Ok(Self::default());
+        }
+        let remote = match &deser.get_slice()?[1..] {
+            b"*" => None,
+            b"d" => Some(false),
+            b"r" => Some(true),
+            _ => return Err(Error::ParseError),
+        };
+        if !deser.has_next() {
+            return Self::new(remote, None);
+        }
+        let dlc = match &deser.get_slice()?[1..] {
+            b"*" => None,
+            [b @ b'0'..=b'8'] => Some(*b - b'0'),
+            _ => return Err(Error::ParseError),
+        };
+        Self::new(remote, dlc)
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        if self.remote.is_none() && self.dlc.is_none() {
+            return Ok(());
+        }
+        ser.add_byte(b',')?;
+        ser.add_byte(match self.remote {
+            None => b'*',
+            Some(false) => b'd',
+            Some(true) => b'r',
+        })?;
+        if let Some(dlc) = self.dlc {
+            ser.add_byte(b',')?;
+            ser.add_uint(dlc)?;
+        }
+        Ok(())
+    }
+}
+
+/// Match pattern for ids as used by the filters
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct IdPattern {
+    extended: bool,
+    ones: u32,
+    zeros: u32,
+}
+
+impl IdPattern {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        let (extended, ones, zeros) = get_ones_zeros(bytes)?;
+        Ok(Self {
+            extended,
+            ones,
+            zeros,
+        })
+    }
+
+    pub fn check(&self, frame: &CanFrame) -> bool {
+        match raw_id(frame.id(), self.extended) {
+            Some(id) => check(id, self.ones, self.zeros, self.extended),
+            None => false,
+        }
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        Self::new(&deser.get_slice()?[1..])
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        add_ones_zeros(ser, self.extended, self.ones, self.zeros);
+        Ok(())
+    }
+}
+
+pub fn raw_id(id: Id, extended: bool) -> Option<u32> {
+    match id {
+        Id::Extended(id) if extended => Some(id.as_raw()),
+        Id::Standard(id) if !extended => Some(id.as_raw() as u32),
+        _ => None,
+    }
 }
 
 pub fn check(id: u32, ones: u32, zeros: u32, extended: bool) -> bool {
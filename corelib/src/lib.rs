@@ -1,7 +1,23 @@
 #![no_std]
 
+mod auth;
+mod cache;
+mod discovery;
 mod filter;
+mod mdns;
+mod peer;
+mod rules;
+mod stream;
+mod tx;
 mod utils;
 
-pub use filter::{NFilters, PFilters};
+pub use auth::*;
+pub use cache::*;
+pub use discovery::*;
+pub use filter::{FilterSet, IdPattern, NFilters, PFilters};
+pub use mdns::*;
+pub use peer::*;
+pub use rules::*;
+pub use stream::*;
+pub use tx::*;
 pub use utils::*;
@@ -0,0 +1,387 @@
+use crate::Error;
+use heapless::{String, Vec};
+
+pub const MDNS_PORT: u16 = 5353;
+pub const MDNS_ADDR: [u8; 4] = [224, 0, 0, 251];
+pub const MAX_NAME_LEN: usize = 255;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_SRV: u16 = 33;
+pub const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+// top bit of the class, the unicast response bit in questions, the cache flush bit in records
+const CLASS_FLAG: u16 = 0x8000;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+const HEADER_LEN: usize = 12;
+const MAX_LABEL_LEN: usize = 63;
+// compression pointers followed in one name, protects against loops
+const MAX_POINTERS: usize = 16;
+
+/// Dotted name in lower case, e.g. `_espcand._tcp.local`
+pub type Name = String<MAX_NAME_LEN>;
+
+fn read_u16(packet: &[u8], offset: usize) -> Result<u16, Error> {
+    match packet.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+        None => Err(Error::ParseError),
+    }
+}
+
+fn read_u32(packet: &[u8], offset: usize) -> Result<u32, Error> {
+    Ok((read_u16(packet, offset)? as u32) << 16 | read_u16(packet, offset + 2)? as u32)
+}
+
+/// Reads the name at the offset, returns the name and the offset behind it
+///
+/// Names are compared case-insensitively, so they are returned in lower case.
+pub fn read_name(packet: &[u8], offset: usize) -> Result<(Name, usize), Error> {
+    let mut name = Name::new();
+    let mut pos = offset;
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let len = *packet.get(pos).ok_or(Error::ParseError)? as usize;
+        match len {
+            0 => break,
+            0xc0.. => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(Error::ParseError);
+                }
+                end.get_or_insert(pos + 2);
+                pos = (read_u16(packet, pos)? & 0x3fff) as usize;
+            }
+            1..=MAX_LABEL_LEN => {
+                let label = packet
+                    .get(pos + 1..pos + 1 + len)
+                    .ok_or(Error::ParseError)?;
+                if !name.is_empty() {
+                    name.push('.').map_err(|_| Error::ParseError)?;
+                }
+                for b in label {
+                    let c = b.to_ascii_lowercase() as char;
+                    name.push(c).map_err(|_| Error::ParseError)?;
+                }
+                pos += 1 + len;
+            }
+            _ => return Err(Error::ParseError),
+        }
+    }
+    Ok((name, end.unwrap_or(pos + 1)))
+}
+
+pub struct Question {
+    pub name: Name,
+    pub qtype: u16,
+    /// The asking host prefers a unicast response
+    pub unicast: bool,
+}
+
+pub struct Record<'a> {
+    pub name: Name,
+    pub rtype: u16,
+    pub ttl: u32,
+    pub cache_flush: bool,
+    /// Offset of the data in the packet, names in the data may point to the packet
+    pub data_offset: usize,
+    pub data: &'a [u8],
+}
+
+/// Reader of a DNS message
+pub struct Message<'a> {
+    packet: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    pub fn new(packet: &'a [u8]) -> Result<Self, Error> {
+        if packet.len() < HEADER_LEN {
+            return Err(Error::ParseError);
+        }
+        Ok(Self { packet })
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.packet[2] & 0x80 != 0
+    }
+
+    fn count(&self, idx: usize) -> usize {
+        // the header was checked by new
+        read_u16(self.packet, 4 + 2 * idx).unwrap_or_default() as usize
+    }
+
+    fn skip_questions(&self) -> Result<usize, Error> {
+        let mut pos = HEADER_LEN;
+        for _ in 0..self.count(0) {
+            pos = read_name(self.packet, pos)?.1 + 4;
+        }
+        Ok(pos)
+    }
+
+    /// Questions of the message, the questions of the Internet class only
+    pub fn questions<const N: usize>(&self) -> Result<Vec<Question, N>, Error> {
+        let mut questions = Vec::new();
+        let mut pos = HEADER_LEN;
+        for _ in 0..self.count(0) {
+            let (name, next) = read_name(self.packet, pos)?;
+            let qtype = read_u16(self.packet, next)?;
+            let class = read_u16(self.packet, next + 2)?;
+            pos = next + 4;
+            if matches!(class & !CLASS_FLAG, CLASS_IN | CLASS_ANY) {
+                let question = Question {
+                    name,
+                    qtype,
+                    unicast: class & CLASS_FLAG != 0,
+                };
+                questions.push(question).map_err(|_| Error::BufIsFull)?;
+            }
+        }
+        Ok(questions)
+    }
+
+    /// Records of the answer, authority and additional sections
+    pub fn records<const N: usize>(&self) -> Result<Vec<Record<'a>, N>, Error> {
+        let mut records = Vec::new();
+        let mut pos = self.skip_questions()?;
+        for _ in 0..self.count(1) + self.count(2) + self.count(3) {
+            let (name, next) = read_name(self.packet, pos)?;
+            let rtype = read_u16(self.packet, next)?;
+            let class = read_u16(self.packet, next + 2)?;
+            let ttl = read_u32(self.packet, next + 4)?;
+            let len = read_u16(self.packet, next + 8)? as usize;
+            let data_offset = next + 10;
+            let data = self
+                .packet
+                .get(data_offset..data_offset + len)
+                .ok_or(Error::ParseError)?;
+            pos = data_offset + len;
+            let record = Record {
+                name,
+                rtype,
+                ttl,
+                cache_flush: class & CLASS_FLAG != 0,
+                data_offset,
+                data,
+            };
+            records.push(record).map_err(|_| Error::BufIsFull)?;
+        }
+        Ok(records)
+    }
+}
+
+/// Data of the records the bridge writes
+pub enum RecordData<'a> {
+    A([u8; 4]),
+    Ptr(&'a str),
+    Srv { port: u16, target: &'a str },
+    Txt(&'a [&'a str]),
+}
+
+impl RecordData<'_> {
+    fn rtype(&self) -> u16 {
+        match self {
+            Self::A(_) => TYPE_A,
+            Self::Ptr(_) => TYPE_PTR,
+            Self::Srv { .. } => TYPE_SRV,
+            Self::Txt(_) => TYPE_TXT,
+        }
+    }
+}
+
+/// Writer of a DNS message, names are written without compression
+pub struct MessageWriter<const CAP: usize> {
+    buf: Vec<u8, CAP>,
+    questions: u16,
+    answers: u16,
+    additionals: u16,
+}
+
+impl<const CAP: usize> MessageWriter<CAP> {
+    pub fn query() -> Self {
+        Self::new(0)
+    }
+
+    /// An mDNS response is always authoritative
+    pub fn response() -> Self {
+        Self::new(FLAG_RESPONSE | FLAG_AUTHORITATIVE)
+    }
+
+    fn new(flags: u16) -> Self {
+        let mut buf = Vec::new();
+        // the header is written again by finish
+        buf.resize(HEADER_LEN, 0).unwrap();
+        buf[2..4].copy_from_slice(&flags.to_be_bytes());
+        Self {
+            buf,
+            questions: 0,
+            answers: 0,
+            additionals: 0,
+        }
+    }
+
+    fn add_slice(&mut self, slice: &[u8]) -> Result<(), Error> {
+        self.buf
+            .extend_from_slice(slice)
+            .map_err(|_| Error::BufIsFull)
+    }
+
+    fn add_name(&mut self, name: &str) -> Result<(), Error> {
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > MAX_LABEL_LEN {
+                return Err(Error::ParseError);
+            }
+            self.add_slice(&[label.len() as u8])?;
+            self.add_slice(label.as_bytes())?;
+        }
+        self.add_slice(&[0])
+    }
+
+    pub fn question(&mut self, name: &str, qtype: u16, unicast: bool) -> Result<(), Error> {
+        let class = if unicast {
+            CLASS_IN | CLASS_FLAG
+        } else {
+            CLASS_IN
+        };
+        self.add_name(name)?;
+        self.add_slice(&qtype.to_be_bytes())?;
+        self.add_slice(&class.to_be_bytes())?;
+        self.questions += 1;
+        Ok(())
+    }
+
+    fn record(
+        &mut self,
+        name: &str,
+        data: &RecordData,
+        cache_flush: bool,
+        ttl: u32,
+    ) -> Result<(), Error> {
+        let class = if cache_flush {
+            CLASS_IN | CLASS_FLAG
+        } else {
+            CLASS_IN
+        };
+        self.add_name(name)?;
+        self.add_slice(&data.rtype().to_be_bytes())?;
+        self.add_slice(&class.to_be_bytes())?;
+        self.add_slice(&ttl.to_be_bytes())?;
+        // the length is written behind the data
+        let start = self.buf.len() + 2;
+        self.add_slice(&[0, 0])?;
+        match data {
+            RecordData::A(addr) => self.add_slice(addr)?,
+            RecordData::Ptr(target) => self.add_name(target)?,
+            RecordData::Srv { port, target } => {
+                // priority and weight
+                self.add_slice(&[0, 0, 0, 0])?;
+                self.add_slice(&port.to_be_bytes())?;
+                self.add_name(target)?;
+            }
+            RecordData::Txt(entries) => {
+                for entry in entries.iter() {
+                    let len = u8::try_from(entry.len()).map_err(|_| Error::ParseError)?;
+                    self.add_slice(&[len])?;
+                    self.add_slice(entry.as_bytes())?;
+                }
+            }
+        }
+        let len = (self.buf.len() - start) as u16;
+        self.buf[start - 2..start].copy_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+
+    /// Answers have to be written before the additional records
+    pub fn answer(
+        &mut self,
+        name: &str,
+        data: &RecordData,
+        cache_flush: bool,
+        ttl: u32,
+    ) -> Result<(), Error> {
+        if self.additionals != 0 {
+            return Err(Error::NotSupported);
+        }
+        self.record(name, data, cache_flush, ttl)?;
+        self.answers += 1;
+        Ok(())
+    }
+
+    pub fn additional(
+        &mut self,
+        name: &str,
+        data: &RecordData,
+        cache_flush: bool,
+        ttl: u32,
+    ) -> Result<(), Error> {
+        self.record(name, data, cache_flush, ttl)?;
+        self.additionals += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<u8, CAP> {
+        self.buf[4..6].copy_from_slice(&self.questions.to_be_bytes());
+        self.buf[6..8].copy_from_slice(&self.answers.to_be_bytes());
+        self.buf[10..12].copy_from_slice(&self.additionals.to_be_bytes());
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_names() {
+        // "a.local" at 12, "b" with a pointer to "local" at 21
+        let mut packet = [0_u8; 25];
+        packet[12..21].copy_from_slice(b"\x01a\x05local\x00");
+        packet[21..25].copy_from_slice(b"\x01B\xc0\x0e");
+        assert_eq!(read_name(&packet, 12).unwrap().0.as_str(), "a.local");
+        let (name, end) = read_name(&packet, 21).unwrap();
+        assert_eq!(name.as_str(), "b.local");
+        assert_eq!(end, 25);
+
+        // a pointer to itself
+        packet[21..23].copy_from_slice(b"\xc0\x15");
+        assert_eq!(read_name(&packet, 21), Err(Error::ParseError));
+        assert_eq!(read_name(&packet[..16], 12), Err(Error::ParseError));
+    }
+
+    #[test]
+    fn write_read() {
+        let mut writer = MessageWriter::<256>::response();
+        writer
+            .answer("host.local", &RecordData::A([10, 0, 0, 1]), true, 120)
+            .unwrap();
+        let txt = RecordData::Txt(&["a=1", "b=2"]);
+        writer
+            .additional("x._s._tcp.local", &txt, true, 4500)
+            .unwrap();
+        assert_eq!(
+            writer.answer("host.local", &RecordData::A([10, 0, 0, 1]), true, 120),
+            Err(Error::NotSupported)
+        );
+        let packet = writer.finish();
+
+        let message = Message::new(&packet).unwrap();
+        assert!(message.is_response());
+        assert!(message.questions::<4>().unwrap().is_empty());
+        let records = message.records::<4>().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name.as_str(), "host.local");
+        assert_eq!(
+            (records[0].rtype, records[0].ttl, records[0].cache_flush),
+            (TYPE_A, 120, true)
+        );
+        assert_eq!(records[0].data, &[10, 0, 0, 1]);
+        assert_eq!(records[1].rtype, TYPE_TXT);
+        assert_eq!(records[1].data, b"\x03a=1\x03b=2");
+
+        assert!(Message::new(&packet[..11]).is_err());
+        let message = Message::new(&packet[..packet.len() - 1]).unwrap();
+        assert!(message.records::<4>().is_err());
+    }
+}
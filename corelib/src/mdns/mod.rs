@@ -0,0 +1,5 @@
+mod message;
+mod responder;
+
+pub use message::*;
+pub use responder::*;
@@ -0,0 +1,291 @@
+use super::message::*;
+use crate::{BridgeInfo, Error};
+use core::fmt::Write;
+use heapless::{String, Vec};
+
+/// Service type advertised by the bridges
+pub const MDNS_SERVICE: &str = "_espcand._tcp.local";
+/// Maximum length of a response, all records fit into it
+pub const MDNS_PACKET_LEN: usize = 512;
+
+// pseudo service listing the service types of a host
+const SERVICES: &str = "_services._dns-sd._udp.local";
+// time to live of the records bound to the address, and of the other records
+const HOST_TTL: u32 = 120;
+const SERVICE_TTL: u32 = 4500;
+// questions of a query answered, further questions are ignored
+const QUESTIONS: usize = 8;
+
+// records of the bridge as a set of bits
+const A: u8 = 0x01;
+const PTR: u8 = 0x02;
+const SRV: u8 = 0x04;
+const TXT: u8 = 0x08;
+const SERVICES_PTR: u8 = 0x10;
+const ALL: u8 = A | PTR | SRV | TXT | SERVICES_PTR;
+
+/// Answers the mDNS queries for the bridge
+///
+/// The bridge is found as `<name>.local` and as the instance `<name>._espcand._tcp.local`
+/// of the service `_espcand._tcp.local`. The instance points to the session port and its
+/// TXT record holds the version and the bitrate. The name contains the MAC address, so it
+/// is unique and the bridge does not probe for it before announcing it.
+pub struct MdnsResponder {
+    info: BridgeInfo,
+    host: Name,
+    instance: Name,
+    version: String<32>,
+    bitrate: String<24>,
+}
+
+impl MdnsResponder {
+    pub fn new(info: BridgeInfo) -> Result<Self, Error> {
+        let mut host = Name::new();
+        let mut instance = Name::new();
+        let mut version = String::new();
+        let mut bitrate = String::new();
+        write!(host, "{}.local", info.name()).map_err(|_| Error::BufIsFull)?;
+        write!(instance, "{}.{MDNS_SERVICE}", info.name()).map_err(|_| Error::BufIsFull)?;
+        write!(version, "version={}", info.version()).map_err(|_| Error::BufIsFull)?;
+        write!(bitrate, "bitrate={}", info.bitrate()).map_err(|_| Error::BufIsFull)?;
+        Ok(Self {
+            info,
+            host,
+            instance,
+            version,
+            bitrate,
+        })
+    }
+
+    /// Host name, e.g. `espcand-a0b1c2d3e4f5.local`
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Service instance, e.g. `espcand-a0b1c2d3e4f5._espcand._tcp.local`
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// Response to a query, `None` if the query is not about the bridge
+    pub fn answer<const CAP: usize>(&self, query: &[u8]) -> Result<Option<Vec<u8, CAP>>, Error> {
+        let message = Message::new(query)?;
+        if message.is_response() {
+            return Ok(None);
+        }
+        let mut answers = 0;
+        for question in message.questions::<QUESTIONS>()? {
+            answers |= self.matching(&question);
+        }
+        if answers == 0 {
+            return Ok(None);
+        }
+        // the records a host needs next to use the answers
+        let mut additionals = 0;
+        if answers & PTR != 0 {
+            additionals |= SRV | TXT | A;
+        }
+        if answers & SRV != 0 {
+            additionals |= A;
+        }
+        self.write(answers, additionals & !answers).map(Some)
+    }
+
+    /// Unsolicited response with all records, sent when the bridge joins the network
+    pub fn announce<const CAP: usize>(&self) -> Result<Vec<u8, CAP>, Error> {
+        self.write(ALL, 0)
+    }
+
+    fn matching(&self, question: &Question) -> u8 {
+        let name = question.name.as_str();
+        let records = if name == self.host.as_str() {
+            A
+        } else if name == self.instance.as_str() {
+            SRV | TXT
+        } else if name == MDNS_SERVICE {
+            PTR
+        } else if name == SERVICES {
+            SERVICES_PTR
+        } else {
+            return 0;
+        };
+        match question.qtype {
+            TYPE_ANY => records,
+            TYPE_A => records & A,
+            TYPE_PTR => records & (PTR | SERVICES_PTR),
+            TYPE_SRV => records & SRV,
+            TYPE_TXT => records & TXT,
+            _ => 0,
+        }
+    }
+
+    fn write<const CAP: usize>(&self, answers: u8, additionals: u8) -> Result<Vec<u8, CAP>, Error> {
+        let mut writer = MessageWriter::response();
+        for (records, additional) in [(answers, false), (additionals, true)] {
+            for record in [SERVICES_PTR, PTR, SRV, TXT, A] {
+                if records & record == 0 {
+                    continue;
+                }
+                let endpoint = self.info.endpoint();
+                let txt = [self.version.as_str(), self.bitrate.as_str()];
+                // shared records may have answers of other hosts, the others are unique
+                let (name, data, cache_flush, ttl) = match record {
+                    SERVICES_PTR => (SERVICES, RecordData::Ptr(MDNS_SERVICE), false, SERVICE_TTL),
+                    PTR => (
+                        MDNS_SERVICE,
+                        RecordData::Ptr(&self.instance),
+                        false,
+                        SERVICE_TTL,
+                    ),
+                    SRV => {
+                        let port = endpoint.port();
+                        let data = RecordData::Srv {
+                            port,
+                            target: &self.host,
+                        };
+                        (self.instance.as_str(), data, true, HOST_TTL)
+                    }
+                    TXT => (
+                        self.instance.as_str(),
+                        RecordData::Txt(&txt),
+                        true,
+                        SERVICE_TTL,
+                    ),
+                    _ => (
+                        self.host.as_str(),
+                        RecordData::A(endpoint.addr()),
+                        true,
+                        HOST_TTL,
+                    ),
+                };
+                match additional {
+                    false => writer.answer(name, &data, cache_flush, ttl)?,
+                    true => writer.additional(name, &data, cache_flush, ttl)?,
+                }
+            }
+        }
+        Ok(writer.finish())
+    }
+}
+
+/// Query for the records of a name, e.g. the PTR records of `_espcand._tcp.local`
+pub fn mdns_query<const CAP: usize>(name: &str, qtype: u16) -> Result<Vec<u8, CAP>, Error> {
+    let mut writer = MessageWriter::query();
+    writer.question(name, qtype, false)?;
+    Ok(writer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Endpoint, bridge_name};
+
+    extern crate std;
+    use std::{string::String, vec::Vec};
+
+    fn responder() -> MdnsResponder {
+        let name = bridge_name(&[0xa0, 0xb1, 0xc2, 0xd3, 0xe4, 0xf5]);
+        let endpoint = Endpoint::new([192, 168, 1, 20], 1234);
+        let info = BridgeInfo::new(&name, endpoint, "0.1.0", 500_000).unwrap();
+        MdnsResponder::new(info).unwrap()
+    }
+
+    fn records(packet: &[u8]) -> Vec<(String, u16)> {
+        let message = Message::new(packet).unwrap();
+        assert!(message.is_response());
+        let records = message.records::<8>().unwrap();
+        records
+            .iter()
+            .map(|r| (r.name.as_str().into(), r.rtype))
+            .collect()
+    }
+
+    #[test]
+    fn browse() {
+        let responder = responder();
+        assert_eq!(
+            responder.instance(),
+            "espcand-a0b1c2d3e4f5._espcand._tcp.local"
+        );
+        let query = mdns_query::<64>("_ESPcand._tcp.local", TYPE_PTR).unwrap();
+        let response = responder
+            .answer::<MDNS_PACKET_LEN>(&query)
+            .unwrap()
+            .unwrap();
+        let instance = responder.instance();
+        let host = responder.host();
+        assert_eq!(
+            records(&response),
+            [
+                (MDNS_SERVICE.into(), TYPE_PTR),
+                (instance.into(), TYPE_SRV),
+                (instance.into(), TYPE_TXT),
+                (host.into(), TYPE_A),
+            ]
+        );
+
+        let message = Message::new(&response).unwrap();
+        let records = message.records::<8>().unwrap();
+        let (target, _) = read_name(&response, records[0].data_offset).unwrap();
+        assert_eq!(target.as_str(), instance);
+        // priority, weight and port, then the host
+        assert_eq!(&records[1].data[..6], &[0, 0, 0, 0, 0x04, 0xd2]);
+        let (target, _) = read_name(&response, records[1].data_offset + 6).unwrap();
+        assert_eq!(target.as_str(), host);
+        assert_eq!(records[2].data, b"\x0dversion=0.1.0\x0ebitrate=500000");
+        assert_eq!(records[3].data, &[192, 168, 1, 20]);
+        assert!(!records[0].cache_flush && records[3].cache_flush);
+    }
+
+    #[test]
+    fn resolve() {
+        let responder = responder();
+        let query = mdns_query::<64>("espcand-A0B1C2D3E4F5.local", TYPE_A).unwrap();
+        let response = responder
+            .answer::<MDNS_PACKET_LEN>(&query)
+            .unwrap()
+            .unwrap();
+        assert_eq!(records(&response), [(responder.host().into(), TYPE_A)]);
+
+        // other names and types are not answered
+        let query = mdns_query::<64>("espcand-a0b1c2d3e4f5.local", TYPE_SRV).unwrap();
+        assert!(
+            responder
+                .answer::<MDNS_PACKET_LEN>(&query)
+                .unwrap()
+                .is_none()
+        );
+        let query = mdns_query::<64>("printer.local", TYPE_ANY).unwrap();
+        assert!(
+            responder
+                .answer::<MDNS_PACKET_LEN>(&query)
+                .unwrap()
+                .is_none()
+        );
+        // nor are responses of other hosts
+        let announcement = responder.announce::<MDNS_PACKET_LEN>().unwrap();
+        assert!(
+            responder
+                .answer::<MDNS_PACKET_LEN>(&announcement)
+                .unwrap()
+                .is_none()
+        );
+        assert!(responder.answer::<MDNS_PACKET_LEN>(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn announce() {
+        let responder = responder();
+        let announcement = responder.announce::<MDNS_PACKET_LEN>().unwrap();
+        let records = records(&announcement);
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0], (SERVICES.into(), TYPE_PTR));
+
+        let query = mdns_query::<64>(SERVICES, TYPE_PTR).unwrap();
+        let response = responder
+            .answer::<MDNS_PACKET_LEN>(&query)
+            .unwrap()
+            .unwrap();
+        assert_eq!(self::records(&response), [(SERVICES.into(), TYPE_PTR)]);
+    }
+}
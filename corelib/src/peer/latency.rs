@@ -0,0 +1,66 @@
+use crate::{DeSerialize, Error, Serialize};
+
+/// Round trip times in milliseconds measured with `$ping`
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Latency {
+    last: u32,
+    min: u32,
+    max: u32,
+}
+
+impl Latency {
+    pub fn new(rtt: u32) -> Self {
+        Self {
+            last: rtt,
+            min: rtt,
+            max: rtt,
+        }
+    }
+
+    pub fn update(&mut self, rtt: u32) {
+        self.last = rtt;
+        self.min = self.min.min(rtt);
+        self.max = self.max.max(rtt);
+    }
+
+    pub fn last(&self) -> u32 {
+        self.last
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let last = deser.get_u32()?;
+        let min = deser.get_u32()?;
+        let max = deser.get_u32()?;
+        Ok(Self { last, min, max })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        for value in [self.last, self.min, self.max] {
+            ser.add_byte(b',')?;
+            ser.add_uint(value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    #[test]
+    fn latency() {
+        let mut latency = Latency::new(20);
+        latency.update(5);
+        latency.update(50);
+        latency.update(12);
+        assert_eq!(latency.last(), 12);
+
+        let mut ser = Ser::<40>::default();
+        latency.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), b",12,5,50");
+        let slice = b",12,5,50\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(Latency::deserialize(&mut deser), Ok(latency));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::CanFrame;
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+
+/// Remembers the frames that crossed a tunnel in one direction for a short time
+///
+/// A frame that comes back from the other direction within the window is a
+/// loop and must not cross again. With a full guard the oldest frame is forgotten.
+pub struct LoopGuard<const CAP: usize> {
+    window: Duration,
+    frames: Vec<(Instant, CanFrame), CAP>,
+}
+
+impl<const CAP: usize> LoopGuard<CAP> {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            frames: Vec::new(),
+        }
+    }
+
+    fn purge(&mut self, now: Instant) {
+        let window = self.window;
+        self.frames.retain(|(instant, _)| *instant + window > now);
+    }
+
+    pub fn record(&mut self, frame: &CanFrame, now: Instant) {
+        self.purge(now);
+        if self.frames.is_full() {
+            self.frames.remove(0);
+        }
+        // there is space after removing the oldest frame
+        let _ = self.frames.push((now, *frame));
+    }
+
+    /// Checks if the frame crossed recently, each recorded frame matches once
+    pub fn check(&mut self, frame: &CanFrame, now: Instant) -> bool {
+        self.purge(now);
+        let position = self
+            .frames
+            .iter()
+            .position(|(_, recorded)| recorded == frame);
+        if let Some(idx) = position {
+            self.frames.remove(idx);
+        }
+        position.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_can::{Frame, StandardId};
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn loop_guard() {
+        let mut guard = LoopGuard::<2>::new(Duration::from_millis(100));
+        let now = Instant::from_millis(1000);
+        guard.record(&s_frame(0x100, &[1]), now);
+        guard.record(&s_frame(0x100, &[1]), now);
+        assert!(!guard.check(&s_frame(0x100, &[2]), now));
+        assert!(guard.check(&s_frame(0x100, &[1]), now));
+        assert!(guard.check(&s_frame(0x100, &[1]), now));
+        assert!(!guard.check(&s_frame(0x100, &[1]), now));
+
+        // the oldest frame is forgotten
+        guard.record(&s_frame(0x100, &[1]), now);
+        guard.record(&s_frame(0x200, &[1]), now);
+        guard.record(&s_frame(0x300, &[1]), now);
+        assert!(!guard.check(&s_frame(0x100, &[1]), now));
+
+        // frames expire after the window
+        assert!(guard.check(&s_frame(0x200, &[1]), Instant::from_millis(1099)));
+        assert!(!guard.check(&s_frame(0x300, &[1]), Instant::from_millis(1100)));
+    }
+}
@@ -0,0 +1,5 @@
+mod latency;
+mod loop_guard;
+
+pub use latency::*;
+pub use loop_guard::*;
@@ -0,0 +1,372 @@
+use crate::{CanFrame, DataPattern, DataTemplate, DeSerialize, Error, IdPattern, Serialize};
+use embassy_time::{Duration, Instant};
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+use heapless::Vec;
+
+/// Bytes of a data mask, bytes beyond its length take a default
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+struct ByteMask {
+    bytes: [u8; 8],
+    len: u8,
+}
+
+impl ByteMask {
+    fn new(slice: &[u8]) -> Result<Self, Error> {
+        let mut bytes = [0; 8];
+        bytes
+            .get_mut(..slice.len())
+            .ok_or(Error::ParseError)?
+            .copy_from_slice(slice);
+        Ok(Self {
+            bytes,
+            len: slice.len() as u8,
+        })
+    }
+
+    fn get(&self, idx: usize, default: u8) -> u8 {
+        self.as_slice().get(idx).copied().unwrap_or(default)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+/// Retransmits a received frame matching the id and data pattern with a translated id and data
+///
+/// The new id is `((id & id_and) | id_or) ^ id_xor` and keeps the type of the received id,
+/// bits beyond the range of the id are dropped.
+/// Without a data template the data is copied unchanged. Each byte n of the new data is then
+/// `((byte & data_and[n]) | data_or[n]) ^ data_xor[n]`, missing mask bytes leave the byte unchanged.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct GatewayRule {
+    slot: u8,
+    interval: u32,
+    id_pattern: IdPattern,
+    data_pattern: DataPattern,
+    id_and: u32,
+    id_or: u32,
+    id_xor: u32,
+    data: Option<DataTemplate>,
+    data_and: ByteMask,
+    data_or: ByteMask,
+    data_xor: ByteMask,
+}
+
+impl GatewayRule {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        slot: u8,
+        interval: u32,
+        id_pattern: IdPattern,
+        data_pattern: DataPattern,
+        id_and: u32,
+        id_or: u32,
+        id_xor: u32,
+        data: Option<DataTemplate>,
+    ) -> Self {
+        Self {
+            slot,
+            interval,
+            id_pattern,
+            data_pattern,
+            id_and,
+            id_or,
+            id_xor,
+            data,
+            data_and: ByteMask::default(),
+            data_or: ByteMask::default(),
+            data_xor: ByteMask::default(),
+        }
+    }
+
+    /// Sets the masks applied to each data byte after the template, up to 8 bytes each
+    pub fn with_data_masks(mut self, and: &[u8], or: &[u8], xor: &[u8]) -> Result<Self, Error> {
+        self.data_and = ByteMask::new(and)?;
+        self.data_or = ByteMask::new(or)?;
+        self.data_xor = ByteMask::new(xor)?;
+        Ok(self)
+    }
+
+    fn has_data_masks(&self) -> bool {
+        self.data_and.len > 0 || self.data_or.len > 0 || self.data_xor.len > 0
+    }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    fn map_id(&self, raw: u32) -> u32 {
+        ((raw & self.id_and) | self.id_or) ^ self.id_xor
+    }
+
+    /// Returns the translated frame if the frame matches
+    pub fn translate(&self, frame: &CanFrame) -> Option<CanFrame> {
+        if !self.id_pattern.check(frame) || !self.data_pattern.check(frame) {
+            return None;
+        }
+        let id = match frame.id() {
+            Id::Standard(id) => {
+                let raw = self.map_id(id.as_raw() as u32) & StandardId::MAX.as_raw() as u32;
+                Id::Standard(StandardId::new(raw as u16)?)
+            }
+            Id::Extended(id) => {
+                let raw = self.map_id(id.as_raw()) & ExtendedId::MAX.as_raw();
+                Id::Extended(ExtendedId::new(raw)?)
+            }
+        };
+        let (mut data, len) = match self.data {
+            Some(template) => template.build(frame),
+            None => {
+                let mut data = [0_u8; 8];
+                data[..frame.data().len()].copy_from_slice(frame.data());
+                (data, frame.data().len())
+            }
+        };
+        for (idx, byte) in data[..len].iter_mut().enumerate() {
+            *byte = ((*byte & self.data_and.get(idx, 0xff)) | self.data_or.get(idx, 0))
+                ^ self.data_xor.get(idx, 0);
+        }
+        CanFrame::new(id, &data[..len])
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let slot = u8::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?;
+        let interval = deser.get_u32()?;
+        let id_pattern = IdPattern::deserialize(deser)?;
+        let data_pattern = DataPattern::deserialize(deser)?;
+        let id_and = deser.get_u32_hex()?;
+        let id_or = deser.get_u32_hex()?;
+        let id_xor = deser.get_u32_hex()?;
+        let data = match &deser.get_slice()?[1..] {
+            b"*" => None,
+            bytes => Some(DataTemplate::new(bytes)?),
+        };
+        let rule = Self::new(
+            slot,
+            interval,
+            id_pattern,
+            data_pattern,
+            id_and,
+            id_or,
+            id_xor,
+            data,
+        );
+        // the data masks are optional
+        if !deser.has_next() {
+            return Ok(rule);
+        }
+        let and = deser.get_slice_hex()?;
+        let or = deser.get_slice_hex()?;
+        let xor = deser.get_slice_hex()?;
+        rule.with_data_masks(&and, &or, &xor)
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint(self.slot)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.interval)?;
+        self.id_pattern.serialize(ser)?;
+        self.data_pattern.serialize(ser)?;
+        for value in [self.id_and, self.id_or, self.id_xor] {
+            ser.add_byte(b',')?;
+            ser.add_uint_hex(value, 0)?;
+        }
+        match &self.data {
+            Some(template) => template.serialize(ser)?,
+            None => ser.add_slice(b",*")?,
+        }
+        if self.has_data_masks() {
+            for mask in [&self.data_and, &self.data_or, &self.data_xor] {
+                ser.add_byte(b',')?;
+                ser.add_slice_hex(mask.as_slice())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Entry {
+    rule: GatewayRule,
+    last: Option<Instant>,
+}
+
+/// Slots for gateway rules, each rule forwards at most one frame per interval
+pub struct Gateway<const CAP: usize> {
+    entries: [Option<Entry>; CAP],
+}
+
+impl<const CAP: usize> Default for Gateway<CAP> {
+    fn default() -> Self {
+        Self {
+            entries: [None; CAP],
+        }
+    }
+}
+
+impl<const CAP: usize> Gateway<CAP> {
+    /// Sets a rule or replaces the rule in the same slot
+    pub fn set(&mut self, rule: GatewayRule) -> Result<(), Error> {
+        let entry = self
+            .entries
+            .get_mut(rule.slot as usize)
+            .ok_or(Error::InvalidSlot)?;
+        *entry = Some(Entry { rule, last: None });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, slot: u8) -> Result<(), Error> {
+        match self.entries.get_mut(slot as usize) {
+            Some(entry @ Some(_)) => {
+                *entry = None;
+                Ok(())
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [None; CAP];
+    }
+
+    /// Returns the translated frames of all matching rules whose interval has passed
+    pub fn process(&mut self, frame: &CanFrame, now: Instant) -> Vec<CanFrame, CAP> {
+        let mut frames = Vec::new();
+        for entry in self.entries.iter_mut().flatten() {
+            let interval = Duration::from_millis(entry.rule.interval as u64);
+            if let Some(last) = entry.last
+                && now < last + interval
+            {
+                continue;
+            }
+            if let Some(translated) = entry.rule.translate(frame) {
+                entry.last = Some(now);
+                // there is one frame per rule at most
+                let _ = frames.push(translated);
+            }
+        }
+        frames
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GatewayRule> + '_ {
+        self.entries.iter().flatten().map(|entry| &entry.rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    fn deser_rule(slice: &[u8]) -> Result<GatewayRule, Error> {
+        let mut deser = DeSer::<100>::from_slice(slice).unwrap();
+        GatewayRule::deserialize(&mut deser)
+    }
+
+    #[test]
+    fn translate() {
+        // 1xx => 2xx, data unchanged
+        let rule = deser_rule(b",0,0,001_****_****,,ff,200,0,*,").unwrap();
+        assert_eq!(
+            rule.translate(&s_frame(0x12a, &[1, 2])),
+            Some(s_frame(0x22a, &[1, 2]))
+        );
+        assert_eq!(rule.translate(&s_frame(0x22a, &[1, 2])), None);
+
+        // flip the lowest bit, swap two bytes and append a fixed byte
+        let rule = deser_rule(b",1,0,***_****_****,01,7ff,0,1,%1%0aa,").unwrap();
+        assert_eq!(
+            rule.translate(&s_frame(0x7ff, &[1, 2])),
+            Some(s_frame(0x7fe, &[2, 1, 0xaa]))
+        );
+        assert_eq!(rule.translate(&s_frame(0x7ff, &[2, 1])), None);
+
+        // clear the high nibble of byte 0, set bit 0 of byte 1 and invert byte 2
+        let rule = deser_rule(b",3,0,***_****_****,,7ff,0,0,*,0f,0001,0000ff,").unwrap();
+        assert_eq!(
+            rule.translate(&s_frame(0x12a, &[0xab, 0x10, 0x0f, 0x55])),
+            Some(s_frame(0x12a, &[0x0b, 0x11, 0xf0, 0x55]))
+        );
+        // the masks do not add bytes
+        assert_eq!(
+            rule.translate(&s_frame(0x12a, &[0xab])),
+            Some(s_frame(0x12a, &[0x0b]))
+        );
+        // the masks are applied after the template
+        let rule = deser_rule(b",4,0,***_****_****,,7ff,0,0,%0aa,,,ff,").unwrap();
+        assert_eq!(
+            rule.translate(&s_frame(0x12a, &[0x01])),
+            Some(s_frame(0x12a, &[0xfe, 0xaa]))
+        );
+
+        // the id keeps its type, higher bits are dropped
+        let rule = deser_rule(b",2,0,***_****_****,,ffffffff,1000,0,*,").unwrap();
+        assert_eq!(
+            rule.translate(&s_frame(0x12a, &[])),
+            Some(s_frame(0x12a, &[]))
+        );
+    }
+
+    #[test]
+    fn gateway() {
+        let mut gateway = Gateway::<2>::default();
+        gateway
+            .set(deser_rule(b",0,100,001_****_****,,ff,200,0,*,").unwrap())
+            .unwrap();
+        gateway
+            .set(deser_rule(b",1,0,001_0010_1010,,7ff,0,0,00,").unwrap())
+            .unwrap();
+        assert_eq!(
+            gateway.set(deser_rule(b",2,0,001_0010_1010,,7ff,0,0,00,").unwrap()),
+            Err(Error::InvalidSlot)
+        );
+
+        let frame = s_frame(0x12a, &[1]);
+        let frames = gateway.process(&frame, Instant::from_millis(1000));
+        assert_eq!(frames, [s_frame(0x22a, &[1]), s_frame(0x12a, &[0])]);
+        // the first rule is rate limited
+        let frames = gateway.process(&frame, Instant::from_millis(1099));
+        assert_eq!(frames, [s_frame(0x12a, &[0])]);
+        let frames = gateway.process(&frame, Instant::from_millis(1100));
+        assert_eq!(frames.len(), 2);
+
+        gateway.remove(1).unwrap();
+        assert_eq!(gateway.remove(1), Err(Error::NotFound));
+        assert_eq!(gateway.iter().count(), 1);
+        gateway.clear();
+        assert_eq!(gateway.iter().count(), 0);
+    }
+
+    #[test]
+    fn gateway_rule_serialize() {
+        for slice in [
+            &b",0,100,001_****_****,,ff,200,0,*,"[..],
+            b",7,0,1_1000_1101_1010_****_****_****_****,02**,1fffffff,0,ff,%1%0aa,",
+            b",3,0,***_****_****,,7ff,0,0,*,0f,0001,0000ff,",
+            b",4,0,***_****_****,,7ff,0,0,%0aa,,,ff,",
+        ] {
+            let rule = deser_rule(slice).unwrap();
+            let mut ser = Ser::<100>::default();
+            rule.serialize(&mut ser).unwrap();
+            assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+        }
+        assert_eq!(
+            deser_rule(b",0,100,001_****_****,,ff,200,0,**,"),
+            Err(Error::ParseError)
+        );
+        assert_eq!(
+            deser_rule(b",0,100,001_****_****,,ff,200,0,*,001122334455667788,,,"),
+            Err(Error::ParseError)
+        );
+        assert_eq!(
+            deser_rule(b",0,100,001_****_****,,ff,200,0,*,ff,\n"),
+            Err(Error::ParseError)
+        );
+    }
+}
@@ -0,0 +1,9 @@
+mod gateway;
+mod pattern;
+mod rule;
+mod template;
+
+pub use gateway::*;
+pub use pattern::*;
+pub use rule::*;
+pub use template::*;
@@ -0,0 +1,112 @@
+use crate::{CanFrame, DeSerialize, Error, Serialize};
+use embedded_can::Frame;
+
+/// Match pattern for the data of a frame, hex nibbles or `*` for any nibble
+///
+/// The pattern matches data frames with at least as many bytes as the pattern.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct DataPattern {
+    value: [u8; 8],
+    mask: [u8; 8],
+    len: u8,
+}
+
+impl DataPattern {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        fn get_nibble(b: u8) -> Result<(u8, u8), Error> {
+            match b {
+                b'0'..=b'9' => Ok((b - b'0', 0xf)),
+                b'a'..=b'f' => Ok((b - b'a' + 10, 0xf)),
+                b'*' => Ok((0, 0)),
+                _ => Err(Error::ParseError),
+            }
+        }
+        if bytes.len() & 0x01 == 1 || bytes.len() > 16 {
+            return Err(Error::ParseError);
+        }
+        let mut pattern = Self::default();
+        for (idx, chunk) in bytes.chunks(2).enumerate() {
+            let (high, high_mask) = get_nibble(chunk[0])?;
+            let (low, low_mask) = get_nibble(chunk[1])?;
+            pattern.value[idx] = high << 4 | low;
+            pattern.mask[idx] = high_mask << 4 | low_mask;
+        }
+        pattern.len = (bytes.len() / 2) as u8;
+        Ok(pattern)
+    }
+
+    pub fn check(&self, frame: &CanFrame) -> bool {
+        let len = self.len as usize;
+        if frame.is_remote_frame() || frame.dlc() < len {
+            return false;
+        }
+        frame.data()[..len]
+            .iter()
+            .zip(self.value.iter().zip(self.mask.iter()))
+            .all(|(data, (value, mask))| data & mask == *value)
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        Self::new(&deser.get_slice()?[1..])
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        #[inline]
+        fn to_x(value: u8, mask: u8) -> u8 {
+            match (value, mask) {
+                (_, 0) => b'*',
+                (0..=9, _) => value + b'0',
+                _ => value - 10 + b'a',
+            }
+        }
+        ser.add_byte(b',')?;
+        for idx in 0..self.len as usize {
+            let (value, mask) = (self.value[idx], self.mask[idx]);
+            ser.add_byte(to_x(value >> 4, mask >> 4))?;
+            ser.add_byte(to_x(value & 0xf, mask & 0xf))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+    use embedded_can::StandardId;
+
+    fn s_frame(data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(0x7e0).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn data_pattern() {
+        let pattern = DataPattern::new(b"22f1*0").unwrap();
+        assert!(pattern.check(&s_frame(&[0x22, 0xf1, 0x90])));
+        assert!(pattern.check(&s_frame(&[0x22, 0xf1, 0x00, 0x55])));
+        assert!(!pattern.check(&s_frame(&[0x22, 0xf1, 0x91])));
+        assert!(!pattern.check(&s_frame(&[0x22, 0xf1])));
+
+        let pattern = DataPattern::new(b"").unwrap();
+        assert!(pattern.check(&s_frame(&[])));
+        let remote = CanFrame::new_remote(StandardId::new(0x7e0).unwrap(), 0).unwrap();
+        assert!(!pattern.check(&remote));
+
+        assert_eq!(DataPattern::new(b"22f"), Err(Error::ParseError));
+        assert_eq!(DataPattern::new(b"2x"), Err(Error::ParseError));
+        assert_eq!(
+            DataPattern::new(b"001122334455667788"),
+            Err(Error::ParseError)
+        );
+    }
+
+    #[test]
+    fn data_pattern_serialize() {
+        let slice = b",22f1*0**,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let pattern = DataPattern::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<40>::default();
+        pattern.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+    }
+}
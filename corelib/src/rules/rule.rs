@@ -0,0 +1,216 @@
+use crate::{CanFrame, DataPattern, DeSerialize, Error, FrameTemplate, IdPattern, Serialize};
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+
+/// Answers a received frame matching the id and data pattern with a frame built from a template
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Rule {
+    slot: u8,
+    delay: u32,
+    id_pattern: IdPattern,
+    data_pattern: DataPattern,
+    response: FrameTemplate,
+}
+
+impl Rule {
+    pub fn new(
+        slot: u8,
+        delay: u32,
+        id_pattern: IdPattern,
+        data_pattern: DataPattern,
+        response: FrameTemplate,
+    ) -> Self {
+        Self {
+            slot,
+            delay,
+            id_pattern,
+            data_pattern,
+            response,
+        }
+    }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    /// Returns the response if the frame matches
+    pub fn respond(&self, frame: &CanFrame) -> Option<CanFrame> {
+        if self.id_pattern.check(frame) && self.data_pattern.check(frame) {
+            Some(self.response.build(frame))
+        } else {
+            None
+        }
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let slot = u8::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?;
+        let delay = deser.get_u32()?;
+        let id_pattern = IdPattern::deserialize(deser)?;
+        let data_pattern = DataPattern::deserialize(deser)?;
+        let response = FrameTemplate::deserialize(deser)?;
+        Ok(Self::new(slot, delay, id_pattern, data_pattern, response))
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint(self.slot)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.delay)?;
+        self.id_pattern.serialize(ser)?;
+        self.data_pattern.serialize(ser)?;
+        self.response.serialize(ser)
+    }
+}
+
+/// Slots for rules and the responses waiting for their delay
+pub struct Rules<const CAP: usize, const PENDING: usize> {
+    rules: [Option<Rule>; CAP],
+    pending: Vec<(Instant, CanFrame), PENDING>,
+}
+
+impl<const CAP: usize, const PENDING: usize> Default for Rules<CAP, PENDING> {
+    fn default() -> Self {
+        Self {
+            rules: [None; CAP],
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<const CAP: usize, const PENDING: usize> Rules<CAP, PENDING> {
+    /// Sets a rule or replaces the rule in the same slot
+    pub fn set(&mut self, rule: Rule) -> Result<(), Error> {
+        let entry = self
+            .rules
+            .get_mut(rule.slot as usize)
+            .ok_or(Error::InvalidSlot)?;
+        *entry = Some(rule);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, slot: u8) -> Result<(), Error> {
+        match self.rules.get_mut(slot as usize) {
+            Some(entry @ Some(_)) => {
+                *entry = None;
+                Ok(())
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    /// Removes all rules and the responses still waiting
+    pub fn clear(&mut self) {
+        self.rules = [None; CAP];
+        self.pending.clear();
+    }
+
+    /// Schedules the responses of all rules matching a received frame
+    ///
+    /// Responses are dropped when too many responses are waiting.
+    pub fn process(&mut self, frame: &CanFrame, now: Instant) -> Result<(), Error> {
+        for rule in self.rules.iter().flatten() {
+            if let Some(response) = rule.respond(frame) {
+                let due = now + Duration::from_millis(rule.delay as u64);
+                self.pending
+                    .push((due, response))
+                    .map_err(|_| Error::BufIsFull)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.iter().map(|(due, _)| *due).min()
+    }
+
+    /// Returns a response that is due at `now`, responses with the same due time keep their order
+    pub fn poll(&mut self, now: Instant) -> Option<CanFrame> {
+        let idx = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(_, (due, _))| *due <= now)
+            .min_by_key(|(_, (due, _))| *due)
+            .map(|(idx, _)| idx)?;
+        Some(self.pending.remove(idx).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> + '_ {
+        self.rules.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+    use embedded_can::{Frame, StandardId};
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    fn rule(slot: u8, delay: u32, data_pattern: &[u8], template: &[u8]) -> Rule {
+        Rule::new(
+            slot,
+            delay,
+            IdPattern::new(b"111_1110_0000").unwrap(),
+            DataPattern::new(data_pattern).unwrap(),
+            FrameTemplate::new(0x7e8, template).unwrap(),
+        )
+    }
+
+    #[test]
+    fn rules() {
+        let mut rules = Rules::<2, 2>::default();
+        rules.set(rule(0, 0, b"3e**", b"7e00")).unwrap();
+        rules.set(rule(1, 50, b"22f190", b"62%1%2aa")).unwrap();
+        assert_eq!(rules.set(rule(2, 0, b"", b"")), Err(Error::InvalidSlot));
+        assert_eq!(rules.iter().count(), 2);
+
+        let now = Instant::from_millis(1000);
+        rules.process(&s_frame(0x7e1, &[0x3e, 0x00]), now).unwrap();
+        assert_eq!(rules.next_due(), None);
+        rules.process(&s_frame(0x7e0, &[0x10, 0x03]), now).unwrap();
+        assert_eq!(rules.next_due(), None);
+
+        rules
+            .process(&s_frame(0x7e0, &[0x22, 0xf1, 0x90]), now)
+            .unwrap();
+        rules.process(&s_frame(0x7e0, &[0x3e, 0x80]), now).unwrap();
+        assert_eq!(
+            rules.process(&s_frame(0x7e0, &[0x3e, 0x00]), now),
+            Err(Error::BufIsFull)
+        );
+        assert_eq!(rules.next_due(), Some(now));
+        assert_eq!(rules.poll(now), Some(s_frame(0x7e8, &[0x7e, 0x00])));
+        assert_eq!(rules.poll(now), None);
+        let due = Instant::from_millis(1050);
+        assert_eq!(rules.next_due(), Some(due));
+        assert_eq!(
+            rules.poll(due),
+            Some(s_frame(0x7e8, &[0x62, 0xf1, 0x90, 0xaa]))
+        );
+        assert_eq!(rules.next_due(), None);
+
+        rules.remove(0).unwrap();
+        assert_eq!(rules.remove(0), Err(Error::NotFound));
+        rules.clear();
+        assert_eq!(rules.iter().count(), 0);
+    }
+
+    #[test]
+    fn rule_serialize() {
+        let slice = b",3,20,111_1110_0000,22f1**,7e8,62%1%2,";
+        let mut deser = DeSer::<60>::from_slice(slice).unwrap();
+        let rule = Rule::deserialize(&mut deser).unwrap();
+        assert_eq!(rule.slot(), 3);
+        let mut ser = Ser::<60>::default();
+        rule.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",3,20,111_1110_000,22f1**,7e8,62%1%2,";
+        let mut deser = DeSer::<60>::from_slice(slice).unwrap();
+        assert_eq!(Rule::deserialize(&mut deser), Err(Error::ParseError));
+    }
+}
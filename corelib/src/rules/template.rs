@@ -0,0 +1,159 @@
+use crate::{CanFrame, DeSerialize, Error, Serialize, id_from_raw, id_to_raw};
+use embedded_can::{Frame, Id};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum TemplateByte {
+    /// A fixed byte, written as two hex nibbles
+    Literal(u8),
+    /// A copy of a byte of the request, written as `%` and the index
+    Copy(u8),
+}
+
+/// Data bytes built from a request, each byte is fixed or copied from the request
+///
+/// Bytes copied from beyond the end of the request are 0.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct DataTemplate {
+    bytes: [TemplateByte; 8],
+    len: u8,
+}
+
+impl DataTemplate {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        fn get_nibble(b: u8) -> Result<u8, Error> {
+            match b {
+                b'0'..=b'9' => Ok(b - b'0'),
+                b'a'..=b'f' => Ok(b - b'a' + 10),
+                _ => Err(Error::ParseError),
+            }
+        }
+        if bytes.len() & 0x01 == 1 || bytes.len() > 16 {
+            return Err(Error::ParseError);
+        }
+        let mut template = [TemplateByte::Literal(0); 8];
+        for (byte, chunk) in template.iter_mut().zip(bytes.chunks(2)) {
+            *byte = match chunk {
+                [b'%', idx @ b'0'..=b'7'] => TemplateByte::Copy(idx - b'0'),
+                [high, low] => TemplateByte::Literal(get_nibble(*high)? << 4 | get_nibble(*low)?),
+                _ => return Err(Error::ParseError),
+            };
+        }
+        Ok(Self {
+            bytes: template,
+            len: (bytes.len() / 2) as u8,
+        })
+    }
+
+    /// Returns the data buffer and the number of bytes used
+    pub fn build(&self, request: &CanFrame) -> ([u8; 8], usize) {
+        let mut data = [0_u8; 8];
+        for (byte, template) in data.iter_mut().zip(self.bytes()) {
+            *byte = match *template {
+                TemplateByte::Literal(value) => value,
+                TemplateByte::Copy(idx) => request.data().get(idx as usize).copied().unwrap_or(0),
+            };
+        }
+        (data, self.len as usize)
+    }
+
+    fn bytes(&self) -> &[TemplateByte] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        Self::new(&deser.get_slice()?[1..])
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        for byte in self.bytes() {
+            match *byte {
+                TemplateByte::Literal(value) => ser.add_slice_hex(&[value])?,
+                TemplateByte::Copy(idx) => {
+                    ser.add_byte(b'%')?;
+                    ser.add_uint(idx)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A frame built from a request with a fixed id and a data template
+///
+/// Ids above 7ff are extended ids.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct FrameTemplate {
+    id: Id,
+    data: DataTemplate,
+}
+
+impl FrameTemplate {
+    pub fn new(id: u32, bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            id: id_from_raw(id)?,
+            data: DataTemplate::new(bytes)?,
+        })
+    }
+
+    pub fn build(&self, request: &CanFrame) -> CanFrame {
+        let (data, len) = self.data.build(request);
+        CanFrame::new(self.id, &data[..len]).unwrap()
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let id = deser.get_u32_hex()?;
+        Ok(Self {
+            id: id_from_raw(id)?,
+            data: DataTemplate::deserialize(deser)?,
+        })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint_hex(id_to_raw(self.id), 0)?;
+        self.data.serialize(ser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+    use embedded_can::{ExtendedId, StandardId};
+
+    #[test]
+    fn frame_template() {
+        let request = CanFrame::new(StandardId::new(0x7e0).unwrap(), &[0x22, 0xf1, 0x90]).unwrap();
+        let template = FrameTemplate::new(0x7e8, b"62%1%2aa%7").unwrap();
+        let response = template.build(&request);
+        assert_eq!(response.id(), Id::Standard(StandardId::new(0x7e8).unwrap()));
+        assert_eq!(response.data(), &[0x62, 0xf1, 0x90, 0xaa, 0x00]);
+
+        let template = FrameTemplate::new(0x18daf110, b"").unwrap();
+        let response = template.build(&request);
+        assert_eq!(
+            response.id(),
+            Id::Extended(ExtendedId::new(0x18daf110).unwrap())
+        );
+        assert_eq!(response.dlc(), 0);
+
+        assert_eq!(FrameTemplate::new(0x7e8, b"%8"), Err(Error::ParseError));
+        assert_eq!(FrameTemplate::new(0x7e8, b"6"), Err(Error::ParseError));
+        assert_eq!(
+            FrameTemplate::new(0x7e8, b"001122334455667788"),
+            Err(Error::ParseError)
+        );
+        assert_eq!(FrameTemplate::new(0x2000_0000, b""), Err(Error::ParseError));
+    }
+
+    #[test]
+    fn frame_template_serialize() {
+        let slice = b",7e8,62%1%2aa,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let template = FrameTemplate::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<40>::default();
+        template.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+    }
+}
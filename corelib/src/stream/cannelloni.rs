@@ -0,0 +1,213 @@
+use crate::{CanFrame, Error};
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+use heapless::Vec;
+
+/// Default UDP port of cannelloni
+pub const CANNELLONI_PORT: u16 = 20000;
+/// Maximum length of a cannelloni packet, well below the MTU of WiFi
+pub const CANNELLONI_PACKET_LEN: usize = 512;
+
+const VERSION: u8 = 2;
+const OP_DATA: u8 = 0;
+const HEADER_LEN: usize = 5;
+const FRAME_HEADER_LEN: usize = 5;
+// flags of the SocketCAN id
+const EFF_FLAG: u32 = 0x8000_0000;
+const RTR_FLAG: u32 = 0x4000_0000;
+const ERR_FLAG: u32 = 0x2000_0000;
+const EFF_MASK: u32 = 0x1fff_ffff;
+// flag of the length marking a CAN FD frame, followed by a byte with the FD flags
+const CANFD_FRAME: u8 = 0x80;
+
+/// Collects frames into cannelloni data packets
+///
+/// A packet has a header of 5 bytes: version 2, op code 0 (data), a sequence number
+/// and the number of frames (big endian). Each frame is the SocketCAN id with the
+/// flags (big endian), the length and the data. Remote frames carry no data.
+pub struct CannelloniEncoder<const CAP: usize> {
+    sequence: u8,
+    count: u16,
+    buf: Vec<u8, CAP>,
+}
+
+impl<const CAP: usize> Default for CannelloniEncoder<CAP> {
+    fn default() -> Self {
+        Self {
+            sequence: 0,
+            count: 0,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<const CAP: usize> CannelloniEncoder<CAP> {
+    /// Adds a frame, returns `BufIsFull` if the packet has to be sent first
+    pub fn push(&mut self, frame: &CanFrame) -> Result<(), Error> {
+        if self.count == 0 {
+            self.buf.clear();
+            self.buf
+                .extend_from_slice(&[VERSION, OP_DATA, self.sequence, 0, 0])
+                .map_err(|_| Error::BufIsFull)?;
+        }
+        let mut can_id = match frame.id() {
+            Id::Standard(id) => id.as_raw() as u32,
+            Id::Extended(id) => id.as_raw() | EFF_FLAG,
+        };
+        let mut data = frame.data();
+        if frame.is_remote_frame() {
+            can_id |= RTR_FLAG;
+            data = &[];
+        }
+        if self.buf.len() + FRAME_HEADER_LEN + data.len() > CAP {
+            return Err(Error::BufIsFull);
+        }
+        // the length was checked above
+        let _ = self.buf.extend_from_slice(&can_id.to_be_bytes());
+        let _ = self.buf.push(frame.dlc() as u8);
+        let _ = self.buf.extend_from_slice(data);
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the packet to send, the next packet gets the next sequence number
+    pub fn take(&mut self) -> Option<&[u8]> {
+        if self.count == 0 {
+            return None;
+        }
+        self.buf[3..HEADER_LEN].copy_from_slice(&self.count.to_be_bytes());
+        self.count = 0;
+        self.sequence = self.sequence.wrapping_add(1);
+        Some(&self.buf)
+    }
+}
+
+/// Reads the frames of a cannelloni data packet
+///
+/// CAN FD frames and error frames cannot be transmitted by the bridge and are skipped.
+/// Frames beyond the capacity are dropped, the frames read until then are returned.
+pub fn cannelloni_decode<const N: usize>(packet: &[u8]) -> Result<Vec<CanFrame, N>, Error> {
+    if packet.len() < HEADER_LEN || packet[0] != VERSION || packet[1] != OP_DATA {
+        return Err(Error::ParseError);
+    }
+    let count = u16::from_be_bytes([packet[3], packet[4]]);
+    let mut rest = &packet[HEADER_LEN..];
+    let mut frames = Vec::new();
+    for _ in 0..count {
+        if frames.is_full() {
+            break;
+        }
+        if rest.len() < FRAME_HEADER_LEN {
+            return Err(Error::ParseError);
+        }
+        let can_id = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]);
+        let mut len = rest[4];
+        rest = &rest[FRAME_HEADER_LEN..];
+        let fd = len & CANFD_FRAME != 0;
+        if fd {
+            len &= !CANFD_FRAME;
+            rest = rest.get(1..).ok_or(Error::ParseError)?;
+        }
+        let remote = can_id & RTR_FLAG != 0;
+        let data_len = if remote { 0 } else { len as usize };
+        if rest.len() < data_len {
+            return Err(Error::ParseError);
+        }
+        let (data, tail) = rest.split_at(data_len);
+        rest = tail;
+        if fd || can_id & ERR_FLAG != 0 {
+            continue;
+        }
+
+        let id = if can_id & EFF_FLAG != 0 {
+            ExtendedId::new(can_id & EFF_MASK).map(Id::Extended)
+        } else {
+            StandardId::new((can_id & EFF_MASK) as u16).map(Id::Standard)
+        };
+        let id = id.ok_or(Error::ParseError)?;
+        let frame = match remote {
+            true => CanFrame::new_remote(id, len as usize),
+            false => CanFrame::new(id, data),
+        };
+        // the capacity was checked above
+        let _ = frames.push(frame.ok_or(Error::ParseError)?);
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let mut encoder = CannelloniEncoder::<CANNELLONI_PACKET_LEN>::default();
+        assert!(encoder.take().is_none());
+        let standard = CanFrame::new(StandardId::new(0x123).unwrap(), &[0xde, 0xad]).unwrap();
+        let extended = CanFrame::new_remote(ExtendedId::new(0x1234567).unwrap(), 4).unwrap();
+        encoder.push(&standard).unwrap();
+        encoder.push(&extended).unwrap();
+        assert_eq!(
+            encoder.take().unwrap(),
+            &[
+                2, 0, 0, 0, 2, //
+                0x00, 0x00, 0x01, 0x23, 2, 0xde, 0xad, //
+                0xc1, 0x23, 0x45, 0x67, 4,
+            ]
+        );
+
+        // a packet is filled until the next frame does not fit
+        let frame = CanFrame::new(StandardId::new(0x7ff).unwrap(), &[0; 8]).unwrap();
+        let mut count = 0;
+        while encoder.push(&frame).is_ok() {
+            count += 1;
+        }
+        let packet = encoder.take().unwrap();
+        assert_eq!(&packet[..3], &[2, 0, 1]);
+        assert_eq!(count, (CANNELLONI_PACKET_LEN - 5) / 13);
+        let frames = cannelloni_decode::<64>(packet).unwrap();
+        assert_eq!(frames.len(), count);
+        assert_eq!(frames[0], frame);
+    }
+
+    #[test]
+    fn decode() {
+        let packet = [
+            2, 0, 7, 0, 4, //
+            0x80, 0x00, 0x00, 0x10, 1, 0x55, // extended id 10
+            0x00, 0x00, 0x07, 0xff, 0x88, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, // CAN FD frame
+            0x20, 0x00, 0x00, 0x04, 8, 0, 0, 0, 0, 0, 0, 0, 0, // error frame
+            0x40, 0x00, 0x01, 0x00, 3, // remote frame
+        ];
+        let frames = cannelloni_decode::<4>(&packet).unwrap();
+        assert_eq!(
+            frames.as_slice(),
+            &[
+                CanFrame::new(ExtendedId::new(0x10).unwrap(), &[0x55]).unwrap(),
+                CanFrame::new_remote(StandardId::new(0x100).unwrap(), 3).unwrap(),
+            ]
+        );
+
+        assert_eq!(
+            cannelloni_decode::<4>(&packet[..packet.len() - 1]),
+            Err(Error::ParseError)
+        );
+        assert_eq!(
+            cannelloni_decode::<4>(&[1, 0, 0, 0, 0]),
+            Err(Error::ParseError)
+        );
+        assert_eq!(
+            cannelloni_decode::<4>(&[2, 1, 0, 0, 0]),
+            Err(Error::ParseError)
+        );
+        assert!(cannelloni_decode::<4>(&[2, 0, 0, 0, 0]).unwrap().is_empty());
+
+        // further frames are dropped
+        let frames = cannelloni_decode::<1>(&packet).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data(), &[0x55]);
+    }
+}
@@ -0,0 +1,5 @@
+mod cannelloni;
+mod packet;
+
+pub use cannelloni::*;
+pub use packet::*;
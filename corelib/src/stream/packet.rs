@@ -0,0 +1,167 @@
+use crate::{CanFrame, ComItem, DATAGRAM_LEN, DeSer, Error, Ser, Serialize};
+use heapless::Vec;
+
+/// Maximum length of a stream packet, well below the MTU of WiFi
+pub const STREAM_PACKET_LEN: usize = 512;
+
+/// Collects received frames into UDP packets
+///
+/// A packet starts with `$seq` and the sequence number, followed by `$rf` items.
+/// The sequence number counts the packets, so a host can detect lost packets.
+#[derive(Default)]
+pub struct StreamEncoder<const CAP: usize> {
+    sequence: u32,
+    frames: usize,
+    ser: Ser<CAP>,
+}
+
+impl<const CAP: usize> StreamEncoder<CAP> {
+    /// Adds a frame, returns `BufIsFull` if the packet has to be sent first
+    pub fn push(&mut self, frame: &CanFrame) -> Result<(), Error> {
+        if self.frames == 0 {
+            self.ser = Ser::default();
+            let header = ComItem::Sequence(self.sequence).serialize();
+            self.ser.add_slice(header.as_slice())?;
+        }
+        let item = ComItem::ReceivedFrame(*frame).serialize();
+        if self.ser.len() + item.len() > CAP {
+            return Err(Error::BufIsFull);
+        }
+        self.ser.add_slice(item.as_slice())?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Returns the packet to send, the next packet gets the next sequence number
+    pub fn take(&mut self) -> Option<Ser<CAP>> {
+        if self.frames == 0 {
+            return None;
+        }
+        self.frames = 0;
+        self.sequence = self.sequence.wrapping_add(1);
+        Some(core::mem::take(&mut self.ser))
+    }
+}
+
+/// Reads the frames of the packets sent by a `StreamEncoder`
+///
+/// Packets that arrive after a newer packet are outdated and discarded.
+#[derive(Default)]
+pub struct StreamDecoder {
+    next: Option<u32>,
+    lost: u32,
+}
+
+impl StreamDecoder {
+    pub fn decode<const N: usize>(&mut self, packet: &[u8]) -> Result<Vec<CanFrame, N>, Error> {
+        let mut lines = packet.split_inclusive(|b| *b == b'\n');
+        let sequence = match Self::item(lines.next())? {
+            ComItem::Sequence(sequence) => sequence,
+            _ => return Err(Error::ParseError),
+        };
+        let mut frames = Vec::new();
+        for line in lines {
+            match Self::item(Some(line))? {
+                ComItem::ReceivedFrame(frame) => {
+                    frames.push(frame).map_err(|_| Error::BufIsFull)?
+                }
+                _ => return Err(Error::ParseError),
+            }
+        }
+
+        if let Some(next) = self.next {
+            let gap = sequence.wrapping_sub(next);
+            if gap as i32 > 0 {
+                self.lost = self.lost.wrapping_add(gap);
+            } else if gap != 0 {
+                return Ok(Vec::new());
+            }
+        }
+        self.next = Some(sequence.wrapping_add(1));
+        Ok(frames)
+    }
+
+    fn item(line: Option<&[u8]>) -> Result<ComItem, Error> {
+        let line = line.ok_or(Error::ParseError)?;
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(line)?;
+        ComItem::deserialize(&mut deser)
+    }
+
+    /// Number of packets lost since the first packet
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_can::{ExtendedId, Frame, StandardId};
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn encode_decode() {
+        let mut encoder = StreamEncoder::<STREAM_PACKET_LEN>::default();
+        let mut decoder = StreamDecoder::default();
+        assert!(encoder.take().is_none());
+
+        let frame = s_frame(0x123, &[1, 2, 3]);
+        encoder.push(&frame).unwrap();
+        assert!(!encoder.is_empty());
+        let packet = encoder.take().unwrap();
+        assert!(encoder.is_empty());
+        assert!(packet.as_slice().starts_with(b"$seq,0\n$rf,"));
+        let frames = decoder.decode::<16>(packet.as_slice()).unwrap();
+        assert_eq!(frames.as_slice(), &[frame]);
+
+        // a packet is filled until the next frame does not fit
+        let frame = CanFrame::new(ExtendedId::new(0x1234567).unwrap(), &[0xff; 8]).unwrap();
+        let mut count = 0;
+        while encoder.push(&frame).is_ok() {
+            count += 1;
+        }
+        assert!(count > 10);
+        let packet = encoder.take().unwrap();
+        assert!(packet.len() <= STREAM_PACKET_LEN);
+        assert!(packet.as_slice().starts_with(b"$seq,1\n"));
+        assert_eq!(
+            decoder.decode::<32>(packet.as_slice()).unwrap().len(),
+            count
+        );
+        assert_eq!(decoder.lost(), 0);
+    }
+
+    #[test]
+    fn lost_packets() {
+        let mut encoder = StreamEncoder::<STREAM_PACKET_LEN>::default();
+        let mut decoder = StreamDecoder::default();
+        let packets = [(); 4].map(|()| {
+            encoder.push(&s_frame(0x100, &[])).unwrap();
+            encoder.take().unwrap()
+        });
+        decoder.decode::<4>(packets[0].as_slice()).unwrap();
+        decoder.decode::<4>(packets[3].as_slice()).unwrap();
+        assert_eq!(decoder.lost(), 2);
+        // the late packet is outdated
+        assert!(
+            decoder
+                .decode::<4>(packets[1].as_slice())
+                .unwrap()
+                .is_empty()
+        );
+
+        // every packet starts with the sequence number
+        assert_eq!(decoder.decode::<4>(b""), Err(Error::ParseError));
+        assert_eq!(
+            decoder.decode::<4>(b"$rf,100,00,\n"),
+            Err(Error::ParseError)
+        );
+    }
+}
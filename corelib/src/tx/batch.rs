@@ -0,0 +1,150 @@
+use crate::{DeSerialize, Error, Serialize, TxFrame};
+use heapless::Vec;
+
+/// A frame of a batch with the delay after the previous frame of the batch
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct BatchFrame {
+    delay: u32,
+    tx_frame: TxFrame,
+}
+
+impl BatchFrame {
+    pub fn new(delay: u32, tx_frame: TxFrame) -> Self {
+        Self { delay, tx_frame }
+    }
+
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    pub fn tx_frame(&self) -> &TxFrame {
+        &self.tx_frame
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let delay = deser.get_u32()?;
+        let tx_frame = TxFrame::deserialize(deser)?;
+        Ok(Self { delay, tx_frame })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint(self.delay)?;
+        self.tx_frame.serialize(ser)
+    }
+}
+
+/// Frames that are transmitted one after another without other frames in between
+#[derive(Debug)]
+pub struct TxBatch<const CAP: usize> {
+    frames: Vec<BatchFrame, CAP>,
+    next: usize,
+}
+
+impl<const CAP: usize> Default for TxBatch<CAP> {
+    fn default() -> Self {
+        Self {
+            frames: Vec::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<const CAP: usize> TxBatch<CAP> {
+    pub fn push(&mut self, frame: BatchFrame) -> Result<(), Error> {
+        self.frames.push(frame).map_err(|_| Error::BufIsFull)
+    }
+
+    /// Delay of the next frame after the previous one
+    pub fn next_delay(&self) -> Option<u32> {
+        self.frames.get(self.next).map(BatchFrame::delay)
+    }
+
+    /// Removes the next frame, the memory is released when the batch is finished
+    pub fn pop(&mut self) -> Option<TxFrame> {
+        let tx_frame = *self.frames.get(self.next)?.tx_frame();
+        self.next += 1;
+        if self.next == self.frames.len() {
+            self.clear();
+        }
+        Some(tx_frame)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.next = 0;
+    }
+
+    /// Number of frames still to send
+    pub fn len(&self) -> usize {
+        self.frames.len() - self.next
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_can::{Frame, StandardId};
+
+    use super::*;
+    use crate::{CanFrame, DeSer, Ser};
+
+    fn s_frame(id: u16, data: &[u8]) -> TxFrame {
+        TxFrame::new(CanFrame::new(StandardId::new(id).unwrap(), data).unwrap())
+    }
+
+    #[test]
+    fn tx_batch() {
+        let mut batch = TxBatch::<2>::default();
+        assert_eq!(batch.next_delay(), None);
+        batch
+            .push(BatchFrame::new(0, s_frame(0x7e0, &[1])))
+            .unwrap();
+        batch
+            .push(BatchFrame::new(5, s_frame(0x100, &[2])))
+            .unwrap();
+        assert_eq!(
+            batch.push(BatchFrame::new(5, s_frame(0x100, &[3]))),
+            Err(Error::BufIsFull)
+        );
+        assert_eq!(batch.len(), 2);
+
+        assert_eq!(batch.next_delay(), Some(0));
+        assert_eq!(batch.pop(), Some(s_frame(0x7e0, &[1])));
+        assert_eq!(batch.next_delay(), Some(5));
+        assert_eq!(batch.pop(), Some(s_frame(0x100, &[2])));
+        assert_eq!(batch.pop(), None);
+        assert!(batch.is_empty());
+
+        // the capacity is available again
+        batch
+            .push(BatchFrame::new(0, s_frame(0x7e0, &[1])))
+            .unwrap();
+        batch
+            .push(BatchFrame::new(0, s_frame(0x7e0, &[2])))
+            .unwrap();
+    }
+
+    #[test]
+    fn batch_frame_serialize() {
+        let slice = b",20,12a,3,1a2b3c,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let frame = BatchFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(frame.delay(), 20);
+        assert_eq!(frame.tx_frame().tag(), None);
+        let mut ser = Ser::<40>::default();
+        frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",0,12a,3,1a2b3c,99,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let frame = BatchFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(frame.tx_frame().tag(), Some(99));
+        let mut ser = Ser::<40>::default();
+        frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+    }
+}
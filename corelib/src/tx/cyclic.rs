@@ -0,0 +1,225 @@
+use crate::{CanFrame, DeSerialize, Error, Serialize};
+use embassy_time::{Duration, Instant};
+
+/// A frame the bridge transmits periodically by itself
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct CyclicJob {
+    slot: u8,
+    period: u32,
+    frame: CanFrame,
+    persistent: bool,
+}
+
+impl CyclicJob {
+    pub fn new(slot: u8, period: u32, frame: CanFrame) -> Result<Self, Error> {
+        if period == 0 {
+            return Err(Error::ParseError);
+        }
+        Ok(Self {
+            slot,
+            period,
+            frame,
+            persistent: false,
+        })
+    }
+
+    /// Persistent jobs are saved with `$save` and started when the bridge starts
+    pub fn with_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    pub fn frame(&self) -> &CanFrame {
+        &self.frame
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let slot = u8::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?;
+        let period = deser.get_u32()?;
+        let frame = CanFrame::deserialize(deser)?;
+        let persistent = match deser.has_next() {
+            true => deser.get_bool()?,
+            false => false,
+        };
+        Ok(Self::new(slot, period, frame)?.with_persistent(persistent))
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint(self.slot)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.period)?;
+        self.frame.serialize(ser)?;
+        if self.persistent {
+            ser.add_slice(b",1")?;
+        }
+        Ok(())
+    }
+}
+
+/// Slots for cyclic jobs with their next transmission time
+pub struct CyclicJobs<const CAP: usize> {
+    jobs: [Option<(CyclicJob, Instant)>; CAP],
+}
+
+impl<const CAP: usize> Default for CyclicJobs<CAP> {
+    fn default() -> Self {
+        Self { jobs: [None; CAP] }
+    }
+}
+
+impl<const CAP: usize> CyclicJobs<CAP> {
+    /// Starts a job or replaces the job in the same slot, the first frame is due immediately
+    pub fn start(&mut self, job: CyclicJob, now: Instant) -> Result<(), Error> {
+        let entry = self
+            .jobs
+            .get_mut(job.slot as usize)
+            .ok_or(Error::InvalidSlot)?;
+        *entry = Some((job, now));
+        Ok(())
+    }
+
+    pub fn stop(&mut self, slot: u8) -> Result<(), Error> {
+        match self.jobs.get_mut(slot as usize) {
+            Some(entry @ Some(_)) => {
+                *entry = None;
+                Ok(())
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.jobs = [None; CAP];
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.jobs.iter().flatten().map(|(_, due)| *due).min()
+    }
+
+    /// Returns the frame of one job that is due at `now` and schedules its next transmission
+    ///
+    /// A job that has fallen behind is not caught up, its next transmission is one period after `now`.
+    pub fn poll(&mut self, now: Instant) -> Option<CanFrame> {
+        for (job, due) in self.jobs.iter_mut().flatten() {
+            if *due <= now {
+                let period = Duration::from_millis(job.period as u64);
+                *due += period;
+                if *due <= now {
+                    *due = now + period;
+                }
+                return Some(job.frame);
+            }
+        }
+        None
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CyclicJob> + '_ {
+        self.jobs.iter().flatten().map(|(job, _)| job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_can::{Frame, StandardId};
+
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    fn s_frame(id: u16, data: &[u8]) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn cyclic_jobs() {
+        let mut jobs = CyclicJobs::<4>::default();
+        assert_eq!(jobs.next_due(), None);
+        let job = CyclicJob::new(1, 100, s_frame(0x12a, &[1])).unwrap();
+        jobs.start(job, Instant::from_millis(0)).unwrap();
+        let job = CyclicJob::new(2, 250, s_frame(0x12b, &[2])).unwrap();
+        jobs.start(job, Instant::from_millis(50)).unwrap();
+        let job = CyclicJob::new(4, 250, s_frame(0x12b, &[2])).unwrap();
+        assert_eq!(
+            jobs.start(job, Instant::from_millis(50)),
+            Err(Error::InvalidSlot)
+        );
+
+        assert_eq!(jobs.next_due(), Some(Instant::from_millis(0)));
+        assert_eq!(
+            jobs.poll(Instant::from_millis(0)),
+            Some(s_frame(0x12a, &[1]))
+        );
+        assert_eq!(jobs.poll(Instant::from_millis(0)), None);
+        assert_eq!(jobs.next_due(), Some(Instant::from_millis(50)));
+        assert_eq!(
+            jobs.poll(Instant::from_millis(60)),
+            Some(s_frame(0x12b, &[2]))
+        );
+        assert_eq!(jobs.next_due(), Some(Instant::from_millis(100)));
+        assert_eq!(
+            jobs.poll(Instant::from_millis(101)),
+            Some(s_frame(0x12a, &[1]))
+        );
+        assert_eq!(jobs.next_due(), Some(Instant::from_millis(200)));
+
+        // a job that has fallen behind is not caught up
+        assert_eq!(
+            jobs.poll(Instant::from_millis(1000)),
+            Some(s_frame(0x12a, &[1]))
+        );
+        assert_eq!(
+            jobs.poll(Instant::from_millis(1000)),
+            Some(s_frame(0x12b, &[2]))
+        );
+        assert_eq!(jobs.next_due(), Some(Instant::from_millis(1100)));
+
+        assert_eq!(jobs.iter().count(), 2);
+        jobs.stop(1).unwrap();
+        assert_eq!(jobs.stop(1), Err(Error::NotFound));
+        assert_eq!(jobs.iter().count(), 1);
+        jobs.clear();
+        assert_eq!(jobs.next_due(), None);
+    }
+
+    #[test]
+    fn cyclic_job_serialize() {
+        let slice = b",3,100,12a,3,1a2b3c,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let job = CyclicJob::deserialize(&mut deser).unwrap();
+        assert_eq!(job.slot(), 3);
+        assert_eq!(job.frame().data(), &[0x1a, 0x2b, 0x3c]);
+        assert!(!job.is_persistent());
+        let mut ser = Ser::<40>::default();
+        job.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",3,100,12a,3,1a2b3c,1,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let job = CyclicJob::deserialize(&mut deser).unwrap();
+        assert!(job.is_persistent());
+        let mut ser = Ser::<40>::default();
+        job.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        // a job that is not persistent is written without the flag
+        let slice = b",3,100,12a,3,1a2b3c,0\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let job = CyclicJob::deserialize(&mut deser).unwrap();
+        assert!(!job.is_persistent());
+        let mut ser = Ser::<40>::default();
+        job.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), b",3,100,12a,3,1a2b3c");
+
+        let slice = b",3,0,12a,3,1a2b3c,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(CyclicJob::deserialize(&mut deser), Err(Error::ParseError));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{CanFrame, Error, NFilter, NFilters};
+
+/// Allowlist and blocklist every frame has to pass before it is transmitted
+///
+/// A frame is blocked if any blocklist entry matches, or if there is an allowlist and no entry
+/// of it matches. The lists can only be changed while the interlock is editable. An interlock
+/// saved locked in flash cannot be unlocked by a host, only a changed flash unlocks it.
+pub struct TxInterlock<const CAP: usize> {
+    allowed: NFilters<CAP>,
+    blocked: NFilters<CAP>,
+    editable: bool,
+    locked_in_flash: bool,
+}
+
+impl<const CAP: usize> Default for TxInterlock<CAP> {
+    fn default() -> Self {
+        Self {
+            allowed: NFilters::default(),
+            blocked: NFilters::default(),
+            editable: true,
+            locked_in_flash: false,
+        }
+    }
+}
+
+impl<const CAP: usize> TxInterlock<CAP> {
+    fn check_editable(&self) -> Result<(), Error> {
+        match self.editable {
+            true => Ok(()),
+            false => Err(Error::NotPermitted),
+        }
+    }
+
+    pub fn allow(&mut self, pattern: NFilter) -> Result<(), Error> {
+        self.check_editable()?;
+        self.allowed.add(pattern)
+    }
+
+    pub fn block(&mut self, pattern: NFilter) -> Result<(), Error> {
+        self.check_editable()?;
+        self.blocked.add(pattern)
+    }
+
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.check_editable()?;
+        self.allowed.clear();
+        self.blocked.clear();
+        Ok(())
+    }
+
+    /// Locks or unlocks the interlock on request of a host
+    pub fn set_editable(&mut self, editable: bool) -> Result<(), Error> {
+        if editable && self.locked_in_flash {
+            return Err(Error::NotPermitted);
+        }
+        self.editable = editable;
+        Ok(())
+    }
+
+    /// Restores the state saved in flash
+    pub fn load_editable(&mut self, editable: bool) {
+        self.editable = editable;
+        self.locked_in_flash = !editable;
+    }
+
+    /// The current state has been saved in flash
+    pub fn saved(&mut self) {
+        self.locked_in_flash = !self.editable;
+    }
+
+    pub fn is_editable(&self) -> bool {
+        self.editable
+    }
+
+    pub fn check(&mut self, frame: &CanFrame) -> Result<(), Error> {
+        if self.blocked.check(frame) {
+            return Err(Error::TxBlocked);
+        }
+        if !self.allowed.get_vec_ref().is_empty() && !self.allowed.check(frame) {
+            return Err(Error::TxBlocked);
+        }
+        Ok(())
+    }
+
+    /// Checks a frame without counting it in the statistics, e.g. a frame defined earlier
+    pub fn passes(&self, frame: &CanFrame) -> bool {
+        let allowed = self.allowed.get_vec_ref();
+        let blocked = self.blocked.get_vec_ref();
+        !blocked.iter().any(|pattern| pattern.matches(frame))
+            && (allowed.is_empty() || allowed.iter().any(|pattern| pattern.matches(frame)))
+    }
+
+    pub fn allowed(&self) -> &NFilters<CAP> {
+        &self.allowed
+    }
+
+    pub fn blocked(&self) -> &NFilters<CAP> {
+        &self.blocked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_can::{Frame, StandardId};
+
+    fn s_frame(id: u16) -> CanFrame {
+        CanFrame::new(StandardId::new(id).unwrap(), &[]).unwrap()
+    }
+
+    #[test]
+    fn interlock() {
+        let mut interlock = TxInterlock::<2>::default();
+        assert_eq!(interlock.check(&s_frame(0x7ff)), Ok(()));
+
+        interlock
+            .block(NFilter::new(b"000_0000_****").unwrap())
+            .unwrap();
+        assert_eq!(interlock.check(&s_frame(0x00a)), Err(Error::TxBlocked));
+        assert_eq!(interlock.check(&s_frame(0x7ff)), Ok(()));
+
+        // with an allowlist everything else is blocked
+        interlock
+            .allow(NFilter::new(b"111_1110_****").unwrap())
+            .unwrap();
+        assert_eq!(interlock.check(&s_frame(0x7e0)), Ok(()));
+        assert_eq!(interlock.check(&s_frame(0x7ff)), Err(Error::TxBlocked));
+        assert_eq!(interlock.blocked().get_vec_ref()[0].stats().hits(), 1);
+        assert!(interlock.passes(&s_frame(0x7e0)));
+        assert!(!interlock.passes(&s_frame(0x00a)));
+        assert!(!interlock.passes(&s_frame(0x7ff)));
+        assert_eq!(interlock.blocked().get_vec_ref()[0].stats().hits(), 1);
+
+        interlock.set_editable(false).unwrap();
+        assert_eq!(
+            interlock.allow(NFilter::new(b"***_****_****").unwrap()),
+            Err(Error::NotPermitted)
+        );
+        assert_eq!(interlock.clear(), Err(Error::NotPermitted));
+        interlock.set_editable(true).unwrap();
+        interlock.clear().unwrap();
+        assert_eq!(interlock.check(&s_frame(0x00a)), Ok(()));
+    }
+
+    #[test]
+    fn locked_in_flash() {
+        let mut interlock = TxInterlock::<2>::default();
+        interlock.set_editable(false).unwrap();
+        interlock.saved();
+        assert_eq!(interlock.set_editable(true), Err(Error::NotPermitted));
+        assert!(!interlock.is_editable());
+
+        // only a flash without the lock unlocks it
+        interlock.load_editable(false);
+        assert_eq!(interlock.set_editable(true), Err(Error::NotPermitted));
+        interlock.load_editable(true);
+        interlock.set_editable(false).unwrap();
+        interlock.set_editable(true).unwrap();
+    }
+}
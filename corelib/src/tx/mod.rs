@@ -0,0 +1,13 @@
+mod batch;
+mod cyclic;
+mod interlock;
+mod queue;
+mod responder;
+mod tx_frame;
+
+pub use batch::*;
+pub use cyclic::*;
+pub use interlock::*;
+pub use queue::*;
+pub use responder::*;
+pub use tx_frame::*;
@@ -0,0 +1,180 @@
+use crate::{CanFrame, Error, TxFrame};
+use embassy_time::{Duration, Instant};
+use embedded_can::{Frame, Id};
+use heapless::Vec;
+
+/// Priority of a frame on the bus, the lower key wins the arbitration
+///
+/// The bits are ordered like they are sent on the bus: base id, RTR/SRR, IDE,
+/// extended id, RTR. A standard frame wins against an extended frame with the
+/// same base id, a data frame wins against a remote frame with the same id.
+fn arbitration_key(frame: &CanFrame) -> u32 {
+    let remote = frame.is_remote_frame() as u32;
+    match frame.id() {
+        Id::Standard(id) => (id.as_raw() as u32) << 21 | remote << 20,
+        Id::Extended(id) => {
+            let raw = id.as_raw();
+            (raw >> 18) << 21 | 1 << 20 | 1 << 19 | (raw & 0x3ffff) << 1 | remote
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: u32,
+    seq: u32,
+    tx_frame: TxFrame,
+    deadline: Option<Instant>,
+}
+
+/// Frames waiting for transmission, ordered like the bus arbitration
+///
+/// Frames with the same id leave the queue in the order they were pushed.
+pub struct TxQueue<const CAP: usize> {
+    entries: Vec<Entry, CAP>,
+    seq: u32,
+}
+
+impl<const CAP: usize> Default for TxQueue<CAP> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            seq: 0,
+        }
+    }
+}
+
+impl<const CAP: usize> TxQueue<CAP> {
+    pub fn push(&mut self, tx_frame: TxFrame, now: Instant) -> Result<(), Error> {
+        let entry = Entry {
+            key: arbitration_key(tx_frame.frame()),
+            seq: self.seq,
+            deadline: tx_frame
+                .deadline()
+                .map(|deadline| now + Duration::from_millis(deadline as u64)),
+            tx_frame,
+        };
+        self.entries.push(entry).map_err(|_| Error::BufIsFull)?;
+        self.seq = self.seq.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Removes the frame with the highest priority
+    pub fn pop(&mut self) -> Option<TxFrame> {
+        let idx = self
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, entry)| (entry.key, entry.seq))
+            .map(|(idx, _)| idx)?;
+        let entry = self.entries.swap_remove(idx);
+        if self.entries.is_empty() {
+            self.seq = 0;
+        }
+        Some(entry.tx_frame)
+    }
+
+    /// Removes a frame whose deadline has passed
+    pub fn pop_expired(&mut self, now: Instant) -> Option<TxFrame> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|entry| entry.deadline.is_some_and(|deadline| deadline <= now))?;
+        let entry = self.entries.swap_remove(idx);
+        if self.entries.is_empty() {
+            self.seq = 0;
+        }
+        Some(entry.tx_frame)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.seq = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_can::{ExtendedId, StandardId};
+
+    fn s_frame(id: u16, data: &[u8]) -> TxFrame {
+        TxFrame::new(CanFrame::new(StandardId::new(id).unwrap(), data).unwrap())
+    }
+
+    fn e_frame(id: u32, data: &[u8]) -> TxFrame {
+        TxFrame::new(CanFrame::new(ExtendedId::new(id).unwrap(), data).unwrap())
+    }
+
+    #[test]
+    fn arbitration_order() {
+        let remote = CanFrame::new_remote(StandardId::new(0x100).unwrap(), 0).unwrap();
+        let frames = [
+            e_frame(0x0400_0000, b""),
+            s_frame(0x100, b""),
+            TxFrame::new(remote),
+            e_frame(0x03ff_ffff, b""),
+            s_frame(0x0ff, b""),
+        ];
+        let mut queue = TxQueue::<8>::default();
+        for frame in frames {
+            queue.push(frame, Instant::from_millis(0)).unwrap();
+        }
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.pop(), Some(frames[4]));
+        assert_eq!(queue.pop(), Some(frames[3]));
+        assert_eq!(queue.pop(), Some(frames[1]));
+        assert_eq!(queue.pop(), Some(frames[2]));
+        assert_eq!(queue.pop(), Some(frames[0]));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn fifo_for_same_id() {
+        let mut queue = TxQueue::<4>::default();
+        for b in 0..4 {
+            queue
+                .push(s_frame(0x12a, &[b]), Instant::from_millis(0))
+                .unwrap();
+        }
+        assert_eq!(
+            queue.push(s_frame(0x001, b""), Instant::from_millis(0)),
+            Err(Error::BufIsFull)
+        );
+        queue.pop().unwrap();
+        queue
+            .push(s_frame(0x001, b""), Instant::from_millis(0))
+            .unwrap();
+        assert_eq!(queue.pop(), Some(s_frame(0x001, b"")));
+        for b in 1..4 {
+            assert_eq!(queue.pop(), Some(s_frame(0x12a, &[b])));
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn deadline() {
+        let mut queue = TxQueue::<4>::default();
+        let stale = s_frame(0x200, b"").with_tag(1).with_deadline(10);
+        let fresh = s_frame(0x300, b"").with_deadline(100);
+        queue.push(stale, Instant::from_millis(0)).unwrap();
+        queue.push(fresh, Instant::from_millis(0)).unwrap();
+        queue
+            .push(s_frame(0x400, b""), Instant::from_millis(0))
+            .unwrap();
+        assert_eq!(queue.pop_expired(Instant::from_millis(9)), None);
+        assert_eq!(queue.pop_expired(Instant::from_millis(10)), Some(stale));
+        assert_eq!(queue.pop_expired(Instant::from_millis(99)), None);
+        assert_eq!(queue.pop(), Some(fresh));
+        assert_eq!(queue.pop_expired(Instant::from_millis(1000)), None);
+        assert_eq!(queue.len(), 1);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{CanFrame, DeSerialize, Error, Serialize, id_from_raw, id_to_raw};
+use embedded_can::Frame;
+use heapless::Vec;
+
+/// A data frame the bridge transmits when a remote frame with the same id is received
+///
+/// Ids above 7ff are extended ids.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct RtrResponse {
+    frame: CanFrame,
+}
+
+impl RtrResponse {
+    pub fn new(id: u32, data: &[u8]) -> Result<Self, Error> {
+        let frame = CanFrame::new(id_from_raw(id)?, data).ok_or(Error::ParseError)?;
+        Ok(Self { frame })
+    }
+
+    pub fn frame(&self) -> &CanFrame {
+        &self.frame
+    }
+
+    /// Checks if this response answers the remote frame
+    pub fn matches(&self, frame: &CanFrame) -> bool {
+        frame.is_remote_frame() && frame.id() == self.frame.id()
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let id = deser.get_u32_hex()?;
+        let data = deser.get_slice_hex()?;
+        Self::new(id, &data)
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint_hex(id_to_raw(self.frame.id()), 0)?;
+        ser.add_byte(b',')?;
+        ser.add_slice_hex(self.frame.data())
+    }
+}
+
+/// Table of the responses to remote frames, one response per id
+#[derive(Clone, Default)]
+pub struct RtrResponders<const CAP: usize> {
+    responses: Vec<RtrResponse, CAP>,
+}
+
+impl<const CAP: usize> RtrResponders<CAP> {
+    /// Adds a response or replaces the response for the same id
+    pub fn set(&mut self, response: RtrResponse) -> Result<(), Error> {
+        match self
+            .responses
+            .iter_mut()
+            .find(|entry| entry.frame.id() == response.frame.id())
+        {
+            Some(entry) => {
+                *entry = response;
+                Ok(())
+            }
+            None => self.responses.push(response).map_err(|_| Error::BufIsFull),
+        }
+    }
+
+    /// Returns the data frame answering a received remote frame
+    pub fn respond(&self, frame: &CanFrame) -> Option<CanFrame> {
+        self.responses
+            .iter()
+            .find(|response| response.matches(frame))
+            .map(|response| response.frame)
+    }
+
+    pub fn clear(&mut self) {
+        self.responses.clear();
+    }
+
+    /// Keeps the responses for which `keep` returns true
+    pub fn retain(&mut self, keep: impl FnMut(&RtrResponse) -> bool) {
+        self.responses.retain(keep);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RtrResponse> + '_ {
+        self.responses.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+    use embedded_can::{ExtendedId, Id, StandardId};
+
+    fn remote(id: impl Into<Id>) -> CanFrame {
+        CanFrame::new_remote(id, 8).unwrap()
+    }
+
+    #[test]
+    fn rtr_responders() {
+        let mut responders = RtrResponders::<2>::default();
+        responders
+            .set(RtrResponse::new(0x12a, &[1]).unwrap())
+            .unwrap();
+        responders
+            .set(RtrResponse::new(0x12a_0000, &[2]).unwrap())
+            .unwrap();
+        responders
+            .set(RtrResponse::new(0x12a, &[3]).unwrap())
+            .unwrap();
+        assert_eq!(responders.len(), 2);
+        assert_eq!(
+            responders.set(RtrResponse::new(0x12b, &[4]).unwrap()),
+            Err(Error::BufIsFull)
+        );
+
+        let response = responders
+            .respond(&remote(StandardId::new(0x12a).unwrap()))
+            .unwrap();
+        assert!(!response.is_remote_frame());
+        assert_eq!(response.data(), &[3]);
+        let response = responders
+            .respond(&remote(ExtendedId::new(0x12a_0000).unwrap()))
+            .unwrap();
+        assert_eq!(response.data(), &[2]);
+        assert_eq!(
+            responders.respond(&remote(ExtendedId::new(0x12a).unwrap())),
+            None
+        );
+        // data frames are not answered
+        let data_frame = CanFrame::new(StandardId::new(0x12a).unwrap(), &[0]).unwrap();
+        assert_eq!(responders.respond(&data_frame), None);
+
+        responders.retain(|response| response.frame().is_standard());
+        assert_eq!(responders.len(), 1);
+        responders.clear();
+        assert!(responders.is_empty());
+    }
+
+    #[test]
+    fn rtr_response_serialize() {
+        let slice = b",1fffffff,0011223344556677,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let response = RtrResponse::deserialize(&mut deser).unwrap();
+        assert!(response.frame().is_extended());
+        let mut ser = Ser::<40>::default();
+        response.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",7ff,,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let response = RtrResponse::deserialize(&mut deser).unwrap();
+        assert!(!response.frame().is_extended());
+        assert_eq!(response.frame().dlc(), 0);
+        let mut ser = Ser::<40>::default();
+        response.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",20000000,00,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(RtrResponse::deserialize(&mut deser), Err(Error::ParseError));
+    }
+}
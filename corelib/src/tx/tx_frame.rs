@@ -0,0 +1,176 @@
+use crate::{CanFrame, DeSerialize, Error, Serialize};
+
+/// A frame to transmit on the bus with its transmit options
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct TxFrame {
+    frame: CanFrame,
+    tag: Option<u32>,
+    deadline: Option<u32>,
+    timeout: Option<u32>,
+    origin: Option<u8>,
+}
+
+impl TxFrame {
+    pub fn new(frame: CanFrame) -> Self {
+        Self {
+            frame,
+            tag: None,
+            deadline: None,
+            timeout: None,
+            origin: None,
+        }
+    }
+
+    /// Transmission results of tagged frames are reported to the host
+    pub fn with_tag(mut self, tag: u32) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Frames still queued `deadline` milliseconds after queueing are dropped
+    pub fn with_deadline(mut self, deadline: u32) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Aborts the transmission if the frame is not acknowledged within `timeout` milliseconds
+    pub fn with_timeout(mut self, timeout: u32) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Session that queued the frame, it gets the transmission results
+    ///
+    /// The origin is kept by the bridge and is not part of the protocol.
+    pub fn with_origin(mut self, session: u8) -> Self {
+        self.origin = Some(session);
+        self
+    }
+
+    pub fn frame(&self) -> &CanFrame {
+        &self.frame
+    }
+
+    pub fn tag(&self) -> Option<u32> {
+        self.tag
+    }
+
+    pub fn deadline(&self) -> Option<u32> {
+        self.deadline
+    }
+
+    pub fn timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    pub fn origin(&self) -> Option<u8> {
+        self.origin
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let frame = CanFrame::deserialize(deser)?;
+        // the options are optional, empty options are skipped
+        let mut options = [None; 3];
+        for option in options.iter_mut() {
+            if !deser.has_next() {
+                break;
+            }
+            *option = deser.get_u32_opt()?;
+        }
+        let [tag, deadline, timeout] = options;
+        if timeout == Some(0) {
+            return Err(Error::ParseError);
+        }
+        Ok(Self {
+            frame,
+            tag,
+            deadline,
+            timeout,
+            origin: None,
+        })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        self.frame.serialize(ser)?;
+        let options = [self.tag, self.deadline, self.timeout];
+        let len = options
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |idx| idx + 1);
+        for option in &options[..len] {
+            ser.add_byte(b',')?;
+            if let Some(value) = option {
+                ser.add_uint(*value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    #[test]
+    fn tx_frame_serialize() {
+        let slice = b",12a,3,1a2b3c,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let tx_frame = TxFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(tx_frame.tag(), None);
+        let mut ser = Ser::<40>::default();
+        tx_frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",12a,3,1a2b3c,4711,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let tx_frame = TxFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(tx_frame.tag(), Some(4711));
+        let mut ser = Ser::<40>::default();
+        tx_frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",12a,3,1a2b3c,4711,100,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let tx_frame = TxFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(tx_frame.tag(), Some(4711));
+        assert_eq!(tx_frame.deadline(), Some(100));
+        let mut ser = Ser::<40>::default();
+        tx_frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",12a,3,1a2b3c,,100,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let tx_frame = TxFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(tx_frame.tag(), None);
+        assert_eq!(tx_frame.deadline(), Some(100));
+        let mut ser = Ser::<40>::default();
+        tx_frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",12a,3,1a2b3c,,,100,";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let tx_frame = TxFrame::deserialize(&mut deser).unwrap();
+        assert_eq!(tx_frame.tag(), None);
+        assert_eq!(tx_frame.deadline(), None);
+        assert_eq!(tx_frame.timeout(), Some(100));
+        let mut ser = Ser::<40>::default();
+        tx_frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        // the origin stays on the bridge
+        let tx_frame = tx_frame.with_origin(2);
+        assert_eq!(tx_frame.origin(), Some(2));
+        let mut ser = Ser::<40>::default();
+        tx_frame.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",12a,3,1a2b3c,1,,0\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(TxFrame::deserialize(&mut deser), Err(Error::ParseError));
+
+        let slice = b",12a,3,1a2b3c,x\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(TxFrame::deserialize(&mut deser), Err(Error::ParseError));
+    }
+}
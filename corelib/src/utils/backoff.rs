@@ -0,0 +1,99 @@
+use crate::{DeSerialize, Error, Serialize};
+
+/// Delays for repeated attempts, doubling from `initial` up to `max` milliseconds
+///
+/// An initial delay of 0 disables the attempts.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Backoff {
+    initial: u32,
+    max: u32,
+    current: u32,
+}
+
+impl Backoff {
+    /// A maximum below the initial delay is raised to the initial delay
+    pub const fn new(initial: u32, max: u32) -> Self {
+        let max = if max < initial { initial } else { max };
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.initial != 0
+    }
+
+    /// Returns the delay before the next attempt
+    pub fn next_delay(&mut self) -> Option<u32> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let delay = self.current;
+        self.current = self.current.saturating_mul(2).min(self.max);
+        Some(delay)
+    }
+
+    /// Starts again with the initial delay after a successful attempt
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let initial = deser.get_u32()?;
+        let max = deser.get_u32()?;
+        Ok(Self::new(initial, max))
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_uint(self.initial)?;
+        ser.add_byte(b',')?;
+        ser.add_uint(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    #[test]
+    fn backoff() {
+        let mut backoff = Backoff::new(100, 500);
+        assert_eq!(backoff.next_delay(), Some(100));
+        assert_eq!(backoff.next_delay(), Some(200));
+        assert_eq!(backoff.next_delay(), Some(400));
+        assert_eq!(backoff.next_delay(), Some(500));
+        assert_eq!(backoff.next_delay(), Some(500));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(100));
+
+        let mut backoff = Backoff::new(0, 500);
+        assert!(!backoff.is_enabled());
+        assert_eq!(backoff.next_delay(), None);
+
+        let mut backoff = Backoff::new(u32::MAX / 2 + 1, 10);
+        assert_eq!(backoff.next_delay(), Some(u32::MAX / 2 + 1));
+        assert_eq!(backoff.next_delay(), Some(u32::MAX / 2 + 1));
+    }
+
+    #[test]
+    fn backoff_serialize() {
+        let slice = b",100,10000\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let backoff = Backoff::deserialize(&mut deser).unwrap();
+        assert_eq!(backoff, Backoff::new(100, 10000));
+        let mut ser = Ser::<40>::default();
+        backoff.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        let slice = b",100,10\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let backoff = Backoff::deserialize(&mut deser).unwrap();
+        let mut ser = Ser::<40>::default();
+        backoff.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), b",100,100");
+    }
+}
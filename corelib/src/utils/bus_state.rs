@@ -0,0 +1,36 @@
+use crate::{DeSerialize, Error, Serialize};
+
+/// State of the CAN controller
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum BusState {
+    /// The controller takes part in the bus communication
+    Active,
+    /// The controller went bus-off after too many transmit errors
+    BusOff,
+    /// The controller was restarted and waits for the bus to become idle
+    Recovering,
+}
+
+impl BusState {
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            Self::Active => b"Active",
+            Self::BusOff => b"BusOff",
+            Self::Recovering => b"Recovering",
+        }
+    }
+
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        match &deser.get_slice()?[1..] {
+            b"Active" => Ok(Self::Active),
+            b"BusOff" => Ok(Self::BusOff),
+            b"Recovering" => Ok(Self::Recovering),
+            _ => Err(Error::ParseError),
+        }
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        ser.add_byte(b',')?;
+        ser.add_slice(self.as_bytes())
+    }
+}
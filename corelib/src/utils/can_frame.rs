@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+#![allow(unused_parens)] // generated by #[bitfield]
 
 use core::fmt::{Display, Formatter};
 
@@ -149,6 +150,24 @@ impl CanFrame {
     }
 }
 
+/// Ids written as one hex number, ids above 7ff are extended ids
+pub(crate) fn id_from_raw(raw: u32) -> Result<Id, Error> {
+    if raw <= StandardId::MAX.as_raw() as u32 {
+        Ok(Id::Standard(
+            StandardId::new(raw as u16).ok_or(Error::ParseError)?,
+        ))
+    } else {
+        Ok(Id::Extended(ExtendedId::new(raw).ok_or(Error::ParseError)?))
+    }
+}
+
+pub(crate) fn id_to_raw(id: Id) -> u32 {
+    match id {
+        Id::Standard(id) => id.as_raw() as u32,
+        Id::Extended(id) => id.as_raw(),
+    }
+}
+
 impl Display for CanFrame {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let mut ser = crate::Ser::<30>::default();
@@ -0,0 +1,84 @@
+use crate::{DeSerialize, Error, Serialize};
+
+/// IPv4 address and TCP port of a remote station
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Endpoint {
+    addr: [u8; 4],
+    port: u16,
+}
+
+impl Endpoint {
+    pub fn new(addr: [u8; 4], port: u16) -> Self {
+        Self { addr, port }
+    }
+
+    pub fn addr(&self) -> [u8; 4] {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The address is written in dotted decimal notation, e.g. `,192.168.1.20,1234`
+    pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
+        let mut addr = [0_u8; 4];
+        let mut parts = deser.get_slice()?[1..].split(|b| *b == b'.');
+        for octet in addr.iter_mut() {
+            let part = parts.next().ok_or(Error::ParseError)?;
+            if part.is_empty() || part.len() > 3 {
+                return Err(Error::ParseError);
+            }
+            let mut value = 0_u32;
+            for b in part {
+                match *b {
+                    b'0'..=b'9' => value = value * 10 + (*b - b'0') as u32,
+                    _ => return Err(Error::ParseError),
+                }
+            }
+            *octet = u8::try_from(value).map_err(|_| Error::ParseError)?;
+        }
+        if parts.next().is_some() {
+            return Err(Error::ParseError);
+        }
+        let port = u16::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?;
+        Ok(Self { addr, port })
+    }
+
+    pub fn serialize(&self, ser: &mut impl Serialize) -> Result<(), Error> {
+        for (idx, octet) in self.addr.iter().enumerate() {
+            ser.add_byte(if idx == 0 { b',' } else { b'.' })?;
+            ser.add_uint(*octet)?;
+        }
+        ser.add_byte(b',')?;
+        ser.add_uint(self.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeSer, Ser};
+
+    #[test]
+    fn endpoint_serialize() {
+        let slice = b",192.168.1.20,1234\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let endpoint = Endpoint::deserialize(&mut deser).unwrap();
+        assert_eq!(endpoint, Endpoint::new([192, 168, 1, 20], 1234));
+        let mut ser = Ser::<40>::default();
+        endpoint.serialize(&mut ser).unwrap();
+        assert_eq!(ser.as_slice(), &slice[..slice.len() - 1]);
+
+        for slice in [
+            &b",192.168.1,1234\n"[..],
+            b",192.168.1.20.1,1234\n",
+            b",192.168.1.256,1234\n",
+            b",192.168..20,1234\n",
+            b",192.168.1.20,65536\n",
+        ] {
+            let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+            assert_eq!(Endpoint::deserialize(&mut deser), Err(Error::ParseError));
+        }
+    }
+}
@@ -21,6 +21,30 @@ pub enum Error {
     NotSupported,
     /// Unknown command
     UnknownCommand,
+    /// The requested item does not exist
+    NotFound,
+    /// The CAN controller is in the bus-off state
+    BusOff,
+    /// The frame could not be transmitted on the CAN bus
+    TxFailed,
+    /// The transmission was aborted because nobody acknowledged the frame in time
+    TxTimeout,
+    /// No batch has been started with $begin
+    NoBatch,
+    /// A batch has already been started with $begin
+    BatchOpen,
+    /// The deadline of the frame passed before it could be transmitted
+    Expired,
+    /// The session is read-only, another session controls the bridge
+    NotPermitted,
+    /// The session has not answered the challenge with `$auth` yet
+    NotAuthenticated,
+    /// The answer to the challenge is wrong
+    AuthFailed,
+    /// The frame was rejected by the transmit interlock
+    TxBlocked,
+    /// The slot is beyond the slots of the table
+    InvalidSlot,
     /// Unknown error
     UnknownError,
 }
@@ -37,6 +61,18 @@ impl From<&[u8]> for Error {
             b"NoBeginFound" => Self::NoBeginFound,
             b"NotSupported" => Self::NotSupported,
             b"UnknownCommand" => Self::UnknownCommand,
+            b"NotFound" => Self::NotFound,
+            b"BusOff" => Self::BusOff,
+            b"TxFailed" => Self::TxFailed,
+            b"TxTimeout" => Self::TxTimeout,
+            b"Expired" => Self::Expired,
+            b"NoBatch" => Self::NoBatch,
+            b"BatchOpen" => Self::BatchOpen,
+            b"NotPermitted" => Self::NotPermitted,
+            b"NotAuthenticated" => Self::NotAuthenticated,
+            b"AuthFailed" => Self::AuthFailed,
+            b"TxBlocked" => Self::TxBlocked,
+            b"InvalidSlot" => Self::InvalidSlot,
             _ => Self::UnknownError,
         }
     }
@@ -54,6 +90,18 @@ impl Error {
             Self::NoBeginFound => b"NoBeginFound",
             Self::NotSupported => b"NotSupported",
             Self::UnknownCommand => b"UnknownCommand",
+            Self::NotFound => b"NotFound",
+            Self::BusOff => b"BusOff",
+            Self::TxFailed => b"TxFailed",
+            Self::TxTimeout => b"TxTimeout",
+            Self::Expired => b"Expired",
+            Self::NoBatch => b"NoBatch",
+            Self::BatchOpen => b"BatchOpen",
+            Self::NotPermitted => b"NotPermitted",
+            Self::NotAuthenticated => b"NotAuthenticated",
+            Self::AuthFailed => b"AuthFailed",
+            Self::TxBlocked => b"TxBlocked",
+            Self::InvalidSlot => b"InvalidSlot",
             Self::UnknownError => b"UnknownError",
         }
     }
@@ -1,44 +1,191 @@
+mod backoff;
+mod bus_state;
 mod can_frame;
+mod endpoint;
 mod error;
 mod rx_buffer;
 mod ser_deser;
 
-pub use crate::filter::{NFilter, PrePFilter};
+pub use crate::auth::{AuthMac, Nonce, Secret};
+pub use crate::cache::IdEntry;
+pub use crate::discovery::BridgeInfo;
+pub use crate::tx::{BatchFrame, CyclicJob, RtrResponse, TxFrame};
+pub use crate::filter::{FilterStats, FramePredicate, NFilter, PrePFilter, Throttle};
+pub use crate::peer::Latency;
+pub use crate::rules::{GatewayRule, Rule};
+pub use backoff::*;
+pub use bus_state::*;
 pub use can_frame::*;
+pub use endpoint::*;
 pub use error::*;
 pub use rx_buffer::*;
 pub use ser_deser::*;
 
-#[derive(Debug)]
+/// Maximum length of a datagram including the next line character
+pub const DATAGRAM_LEN: usize = 128;
+
+#[derive(Debug, Clone)]
 pub enum ComItem {
+    Abort,                      // Host  => Bridge              Discard and stop batches
+    Auth(AuthMac),              // Host  => Bridge              Answer to the challenge
+    AutoRecover(Backoff),       // Host <=> Bridge <=> Flash    Set bus-off recovery back-off
+    BatchFrame(BatchFrame),     // Host  => Bridge              Add frame to batch
+    Begin,                      // Host  => Bridge              Start batch
+    BridgeInfo(BridgeInfo),     // Host <=  Bridge              Answer to a discovery query
+    BusRecover,                 // Host  => Bridge              Recover from bus-off now
+    BusState(BusState),         // Host <=  Bridge              Bus state changed
+    Cannelloni(Option<Endpoint>), // Host <=> Bridge <=> Flash  Set cannelloni peer
     ClearFilters,               // Host  => Bridge              Clear all Filters
+    ClearRtr,                   // Host  => Bridge              Clear remote frame responses
+    ClearStats,                 // Host  => Bridge              Clear filter counters
+    Commit,                     // Host  => Bridge              Transmit batch
+    Cyclic(CyclicJob),          // Host <=> Bridge <=> Flash    Define cyclic transmit job
+    CyclicStop(u8),             // Host  => Bridge              Stop cyclic transmit job
+    Discover,                   // Host  => Bridge              Discovery query via UDP
     Echo,                       // Host <=> Bridge              Test TCP communicatiion
     End,                        //          Bridge <=> Flash    End of Data
     Error(Error),               // Host <=  Bridge              Show errors
-    FrameToSend(CanFrame),      // Host  => Bridge              Send Can Frame
+    FilterStats(FilterStats),   // Host <=  Bridge              Show filter counters
+    FrameToSend(TxFrame),       // Host  => Bridge              Send Can Frame
+    Gateway(GatewayRule),       // Host <=> Bridge <=> Flash    Define gateway rule
+    GatewayDel(u8),             // Host  => Bridge              Delete gateway rule
+    IdInfo(IdEntry),            // Host <=  Bridge              Show seen id
+    LastFrame(CanFrame),        // Host <=  Bridge              Show last frame of an id
+    Lock,                       // Host  => Bridge              Take control of the bridge
     Magic(bool),                //          Bridge <=> Flash    Start sign
     NFilter(NFilter),           // Host <=> Bridge <=> Flash    Define NFilter 
+    Nonce(Nonce),               // Host <=  Bridge              Challenge of a new session
+    Peer(Option<Endpoint>),     // Host <=> Bridge <=> Flash    Set peer bridge
+    PeerLatency(Latency),       // Host <=  Bridge              Show peer round trip times
     PFilter(PrePFilter),        // Host <=> Bridge <=> Flash    Define PFilter 
+    Ping(u32),                  // Host <=> Bridge <=> Bridge   Measure round trip time
+    Pong(u32),                  // Host <=> Bridge <=> Bridge   Answer to Ping
     ReceivedFrame(CanFrame),    // Host <=  Bridge              Can Frame received
+    Rtr(RtrResponse),           // Host <=> Bridge <=> Flash    Define remote frame response
+    Rule(Rule),                 // Host <=> Bridge <=> Flash    Define request/response rule
+    RuleDel(u8),                // Host  => Bridge              Delete request/response rule
+    RxDropped(u32),             // Host <=  Bridge              Received frames dropped
     Save,                       // Host  => Bridge              Save Config to flash
+    Secret(Option<Secret>),     // Host  => Bridge <=> Flash    Set shared secret
+    Sequence(u32),              // Host <=  Bridge              Sequence number of a UDP packet
+    Server(Option<Endpoint>),   // Host <=> Bridge <=> Flash    Set server to connect to
+    ShowCannelloni,             // Host  => Bridge              Show cannelloni peer
+    ShowCyclic,                 // Host  => Bridge              Show cyclic transmit jobs
     ShowFilters,                // Host  => Bridge              Show Filters 
+    ShowGateway,                // Host  => Bridge              Show gateway rules
+    ShowIds,                    // Host  => Bridge              Show all seen ids
+    ShowLast(u32),              // Host  => Bridge              Show last frame of an id
+    ShowPeer,                   // Host  => Bridge              Show peer bridge
+    ShowPeerLatency,            // Host  => Bridge              Show peer round trip times
+    ShowRtr,                    // Host  => Bridge              Show remote frame responses
+    ShowRules,                  // Host  => Bridge              Show request/response rules
+    ShowServer,                 // Host  => Bridge              Show server to connect to
+    ShowTxInterlock,            // Host  => Bridge              Show transmit interlock
+    ShowTxQueue,                // Host  => Bridge              Show transmit queue depth
+    Snapshot(u32),              // Host <=> Bridge <=> Flash    Set snapshot period
+    Stream(u16),                // Host  => Bridge              Stream frames to a UDP port
+    TxAllow(NFilter),           // Host <=> Bridge <=> Flash    Add id to transmit allowlist
+    TxBlock(NFilter),           // Host <=> Bridge <=> Flash    Add id to transmit blocklist
+    TxClear,                    // Host  => Bridge              Clear transmit interlock
+    TxEdit(bool),               // Host <=> Bridge <=> Flash    Allow interlock changes
+    TxErr(u32, Error),          // Host <=  Bridge              Tagged frame not sent
+    TxOk(u32, u32),             // Host <=  Bridge              Tagged frame sent
+    TxQueue(u32),               // Host <=  Bridge              Transmit queue depth
 }
 
 impl ComItem {
     pub fn deserialize(deser: &mut impl DeSerialize) -> Result<Self, Error> {
         let slice = deser.get_slice()?;
         let r = match slice {
+            b"$abort" => ComItem::Abort,
+            b"$auth" => ComItem::Auth(AuthMac::deserialize(deser)?),
+            b"$autorecover" => ComItem::AutoRecover(Backoff::deserialize(deser)?),
+            b"$begin" => ComItem::Begin,
+            b"$bridge" => ComItem::BridgeInfo(BridgeInfo::deserialize(deser)?),
+            b"$bus" => ComItem::BusState(BusState::deserialize(deser)?),
+            b"$busrecover" => ComItem::BusRecover,
+            b"$cannelloni" => ComItem::Cannelloni(match deser.has_next() {
+                true => Some(Endpoint::deserialize(deser)?),
+                false => None,
+            }),
+            b"$cannelloni?" => ComItem::ShowCannelloni,
             b"$clearfilt" => ComItem::ClearFilters,
+            b"$clearrtr" => ComItem::ClearRtr,
+            b"$clearstat" => ComItem::ClearStats,
+            b"$commit" => ComItem::Commit,
+            b"$cyc" => ComItem::Cyclic(CyclicJob::deserialize(deser)?),
+            b"$cyc?" => ComItem::ShowCyclic,
+            b"$cycstop" => ComItem::CyclicStop(
+                u8::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?,
+            ),
+            b"$discover" => ComItem::Discover,
             b"$echo" => ComItem::Echo,
             b"$end" => ComItem::End,
             b"$err" => ComItem::Error(Error::deserialize(deser)?),
-            b"$fts" => ComItem::FrameToSend(CanFrame::deserialize(deser)?),
+            b"$fstat" => ComItem::FilterStats(FilterStats::deserialize(deser)?),
+            b"$fts" => ComItem::FrameToSend(TxFrame::deserialize(deser)?),
+            b"$ftsb" => ComItem::BatchFrame(BatchFrame::deserialize(deser)?),
+            b"$gw" => ComItem::Gateway(GatewayRule::deserialize(deser)?),
+            b"$gw?" => ComItem::ShowGateway,
+            b"$gwdel" => ComItem::GatewayDel(
+                u8::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?,
+            ),
+            b"$id" => ComItem::IdInfo(IdEntry::deserialize(deser)?),
+            b"$ids?" => ComItem::ShowIds,
+            b"$last" => ComItem::LastFrame(CanFrame::deserialize(deser)?),
+            b"$last?" => ComItem::ShowLast(deser.get_u32_hex()?),
+            b"$lock" => ComItem::Lock,
             b"$magic" => ComItem::Magic(Magic::deserialize(deser)?),
             b"$nfilt" => ComItem::NFilter(NFilter::deserialize(deser)?),
+            b"$nonce" => ComItem::Nonce(Nonce::deserialize(deser)?),
+            b"$peer" => ComItem::Peer(match deser.has_next() {
+                true => Some(Endpoint::deserialize(deser)?),
+                false => None,
+            }),
+            b"$peer?" => ComItem::ShowPeer,
+            b"$peerlat" => ComItem::PeerLatency(Latency::deserialize(deser)?),
+            b"$peerlat?" => ComItem::ShowPeerLatency,
             b"$pfilt" => ComItem::PFilter(PrePFilter::deserialize(deser)?),
+            b"$ping" => ComItem::Ping(deser.get_u32()?),
+            b"$pong" => ComItem::Pong(deser.get_u32()?),
             b"$rf" => ComItem::ReceivedFrame(CanFrame::deserialize(deser)?),
+            b"$rtr" => ComItem::Rtr(RtrResponse::deserialize(deser)?),
+            b"$rtr?" => ComItem::ShowRtr,
+            b"$rule" => ComItem::Rule(Rule::deserialize(deser)?),
+            b"$rule?" => ComItem::ShowRules,
+            b"$ruledel" => ComItem::RuleDel(
+                u8::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?,
+            ),
+            b"$rxdrop" => ComItem::RxDropped(deser.get_u32()?),
             b"$save" => ComItem::Save,
+            b"$secret" => ComItem::Secret(match deser.has_next() {
+                true => Some(Secret::deserialize(deser)?),
+                false => None,
+            }),
+            b"$seq" => ComItem::Sequence(deser.get_u32()?),
+            b"$server" => ComItem::Server(match deser.has_next() {
+                true => Some(Endpoint::deserialize(deser)?),
+                false => None,
+            }),
+            b"$server?" => ComItem::ShowServer,
             b"$filt?" => ComItem::ShowFilters,
+            b"$snap" => ComItem::Snapshot(deser.get_u32()?),
+            b"$txallow" => ComItem::TxAllow(NFilter::deserialize(deser)?),
+            b"$txblock" => ComItem::TxBlock(NFilter::deserialize(deser)?),
+            b"$txclear" => ComItem::TxClear,
+            b"$txedit" => ComItem::TxEdit(match deser.get_u32()? {
+                0 => false,
+                1 => true,
+                _ => return Err(Error::ParseError),
+            }),
+            b"$txerr" => ComItem::TxErr(deser.get_u32()?, Error::deserialize(deser)?),
+            b"$txok" => ComItem::TxOk(deser.get_u32()?, deser.get_u32()?),
+            b"$txq" => ComItem::TxQueue(deser.get_u32()?),
+            b"$txint?" => ComItem::ShowTxInterlock,
+            b"$txq?" => ComItem::ShowTxQueue,
+            b"$udp" => ComItem::Stream(
+                u16::try_from(deser.get_u32()?).map_err(|_| Error::ParseError)?,
+            ),
             _ => return Err(Error::ParseError),
         };
         if deser.is_end() {
@@ -48,20 +195,82 @@ impl ComItem {
         }
     }
 
-    pub fn serialize(&self) -> Ser<50> {
-        let mut ser = Ser::<50>::default();
+    pub fn serialize(&self) -> Ser<DATAGRAM_LEN> {
+        let mut ser = Ser::<DATAGRAM_LEN>::default();
         match self {
+            Self::Abort => ser.add_slice(b"$abort").unwrap(),
+            Self::Auth(mac) => {
+                ser.add_slice(b"$auth").unwrap();
+                mac.serialize(&mut ser).unwrap();
+            }
+            Self::AutoRecover(backoff) => {
+                ser.add_slice(b"$autorecover").unwrap();
+                backoff.serialize(&mut ser).unwrap();
+            }
+            Self::BatchFrame(frame) => {
+                ser.add_slice(b"$ftsb").unwrap();
+                frame.serialize(&mut ser).unwrap();
+            }
+            Self::Begin => ser.add_slice(b"$begin").unwrap(),
+            Self::BridgeInfo(info) => {
+                ser.add_slice(b"$bridge").unwrap();
+                info.serialize(&mut ser).unwrap();
+            }
+            Self::BusRecover => ser.add_slice(b"$busrecover").unwrap(),
+            Self::BusState(state) => {
+                ser.add_slice(b"$bus").unwrap();
+                state.serialize(&mut ser).unwrap();
+            }
+            Self::Cannelloni(endpoint) => {
+                ser.add_slice(b"$cannelloni").unwrap();
+                if let Some(endpoint) = endpoint {
+                    endpoint.serialize(&mut ser).unwrap();
+                }
+            }
             Self::ClearFilters => ser.add_slice(b"$clearfilt").unwrap(),
+            Self::ClearRtr => ser.add_slice(b"$clearrtr").unwrap(),
+            Self::ClearStats => ser.add_slice(b"$clearstat").unwrap(),
+            Self::Commit => ser.add_slice(b"$commit").unwrap(),
+            Self::Cyclic(job) => {
+                ser.add_slice(b"$cyc").unwrap();
+                job.serialize(&mut ser).unwrap();
+            }
+            Self::CyclicStop(slot) => {
+                ser.add_slice(b"$cycstop,").unwrap();
+                ser.add_uint(*slot).unwrap();
+            }
+            Self::Discover => ser.add_slice(b"$discover").unwrap(),
             Self::Echo => ser.add_slice(b"$echo").unwrap(),
             Self::End => ser.add_slice(b"$end").unwrap(),
             Self::Error(error) => {
                 ser.add_slice(b"$err").unwrap();
                 error.serialize(&mut ser).unwrap();
             }
-            Self::FrameToSend(frame) => {
+            Self::FilterStats(stats) => {
+                ser.add_slice(b"$fstat").unwrap();
+                stats.serialize(&mut ser).unwrap();
+            }
+            Self::FrameToSend(tx_frame) => {
                 ser.add_slice(b"$fts").unwrap();
+                tx_frame.serialize(&mut ser).unwrap();
+            }
+            Self::Gateway(rule) => {
+                ser.add_slice(b"$gw").unwrap();
+                rule.serialize(&mut ser).unwrap();
+            }
+            Self::GatewayDel(slot) => {
+                ser.add_slice(b"$gwdel,").unwrap();
+                ser.add_uint(*slot).unwrap();
+            }
+            Self::IdInfo(entry) => {
+                ser.add_slice(b"$id").unwrap();
+                entry.serialize(&mut ser).unwrap();
+            }
+            Self::LastFrame(frame) => {
+                ser.add_slice(b"$last").unwrap();
                 frame.serialize(&mut ser).unwrap();
             }
+            Self::Lock => ser.add_slice(b"$lock").unwrap(),
             Self::Magic(_) => {
                 ser.add_slice(b"$magic").unwrap();
                 Magic::serialize(&mut ser).unwrap();
@@ -70,20 +279,199 @@ impl ComItem {
                 ser.add_slice(b"$nfilt").unwrap();
                 nfilter.serialize(&mut ser).unwrap();
             }
+            Self::Nonce(nonce) => {
+                ser.add_slice(b"$nonce").unwrap();
+                nonce.serialize(&mut ser).unwrap();
+            }
+            Self::Peer(endpoint) => {
+                ser.add_slice(b"$peer").unwrap();
+                if let Some(endpoint) = endpoint {
+                    endpoint.serialize(&mut ser).unwrap();
+                }
+            }
+            Self::PeerLatency(latency) => {
+                ser.add_slice(b"$peerlat").unwrap();
+                latency.serialize(&mut ser).unwrap();
+            }
             Self::PFilter(pre_pfilter) => {
                 ser.add_slice(b"$pfilt").unwrap();
                 pre_pfilter.serialize(&mut ser).unwrap();
             }
+            Self::Ping(timestamp) => {
+                ser.add_slice(b"$ping,").unwrap();
+                ser.add_uint(*timestamp).unwrap();
+            }
+            Self::Pong(timestamp) => {
+                ser.add_slice(b"$pong,").unwrap();
+                ser.add_uint(*timestamp).unwrap();
+            }
             Self::ReceivedFrame(frame) => {
                 ser.add_slice(b"$rf").unwrap();
                 frame.serialize(&mut ser).unwrap();
             }
+            Self::Rtr(response) => {
+                ser.add_slice(b"$rtr").unwrap();
+                response.serialize(&mut ser).unwrap();
+            }
+            Self::Rule(rule) => {
+                ser.add_slice(b"$rule").unwrap();
+                rule.serialize(&mut ser).unwrap();
+            }
+            Self::RuleDel(slot) => {
+                ser.add_slice(b"$ruledel,").unwrap();
+                ser.add_uint(*slot).unwrap();
+            }
+            Self::RxDropped(dropped) => {
+                ser.add_slice(b"$rxdrop,").unwrap();
+                ser.add_uint(*dropped).unwrap();
+            }
             Self::Save => ser.add_slice(b"$save").unwrap(),
+            Self::Secret(secret) => {
+                ser.add_slice(b"$secret").unwrap();
+                if let Some(secret) = secret {
+                    secret.serialize(&mut ser).unwrap();
+                }
+            }
+            Self::Sequence(sequence) => {
+                ser.add_slice(b"$seq,").unwrap();
+                ser.add_uint(*sequence).unwrap();
+            }
+            Self::Server(endpoint) => {
+                ser.add_slice(b"$server").unwrap();
+                if let Some(endpoint) = endpoint {
+                    endpoint.serialize(&mut ser).unwrap();
+                }
+            }
+            Self::ShowCannelloni => ser.add_slice(b"$cannelloni?").unwrap(),
+            Self::ShowCyclic => ser.add_slice(b"$cyc?").unwrap(),
             Self::ShowFilters => ser.add_slice(b"$filt?").unwrap(),
+            Self::ShowGateway => ser.add_slice(b"$gw?").unwrap(),
+            Self::ShowRtr => ser.add_slice(b"$rtr?").unwrap(),
+            Self::ShowRules => ser.add_slice(b"$rule?").unwrap(),
+            Self::ShowServer => ser.add_slice(b"$server?").unwrap(),
+            Self::ShowIds => ser.add_slice(b"$ids?").unwrap(),
+            Self::ShowLast(id) => {
+                ser.add_slice(b"$last?,").unwrap();
+                ser.add_uint_hex(*id, 0).unwrap();
+            }
+            Self::ShowPeer => ser.add_slice(b"$peer?").unwrap(),
+            Self::ShowPeerLatency => ser.add_slice(b"$peerlat?").unwrap(),
+            Self::ShowTxInterlock => ser.add_slice(b"$txint?").unwrap(),
+            Self::ShowTxQueue => ser.add_slice(b"$txq?").unwrap(),
+            Self::Snapshot(period) => {
+                ser.add_slice(b"$snap,").unwrap();
+                ser.add_uint(*period).unwrap();
+            }
+            Self::Stream(port) => {
+                ser.add_slice(b"$udp,").unwrap();
+                ser.add_uint(*port).unwrap();
+            }
+            Self::TxAllow(pattern) => {
+                ser.add_slice(b"$txallow").unwrap();
+                pattern.serialize(&mut ser).unwrap();
+            }
+            Self::TxBlock(pattern) => {
+                ser.add_slice(b"$txblock").unwrap();
+                pattern.serialize(&mut ser).unwrap();
+            }
+            Self::TxClear => ser.add_slice(b"$txclear").unwrap(),
+            Self::TxEdit(editable) => {
+                ser.add_slice(b"$txedit,").unwrap();
+                ser.add_uint(*editable as u8).unwrap();
+            }
+            Self::TxErr(tag, error) => {
+                ser.add_slice(b"$txerr,").unwrap();
+                ser.add_uint(*tag).unwrap();
+                error.serialize(&mut ser).unwrap();
+            }
+            Self::TxOk(tag, timestamp) => {
+                ser.add_slice(b"$txok,").unwrap();
+                ser.add_uint(*tag).unwrap();
+                ser.add_byte(b',').unwrap();
+                ser.add_uint(*timestamp).unwrap();
+            }
+            Self::TxQueue(len) => {
+                ser.add_slice(b"$txq,").unwrap();
+                ser.add_uint(*len).unwrap();
+            }
         }
         ser.add_byte(b'\n').unwrap();
         ser
     }
+
+    /// Returns true for commands that change the bridge or the bus and need the controller
+    ///
+    /// Queries and new filters of the own session are allowed for read-only sessions.
+    /// Clearing the filters is left to the controller.
+    pub fn requires_control(&self) -> bool {
+        match self {
+            Self::Abort
+            | Self::AutoRecover(_)
+            | Self::BatchFrame(_)
+            | Self::Begin
+            | Self::BusRecover
+            | Self::ClearFilters
+            | Self::Cannelloni(_)
+            | Self::ClearRtr
+            | Self::Commit
+            | Self::Cyclic(_)
+            | Self::CyclicStop(_)
+            | Self::FrameToSend(_)
+            | Self::Gateway(_)
+            | Self::GatewayDel(_)
+            | Self::Peer(_)
+            | Self::Rtr(_)
+            | Self::Rule(_)
+            | Self::RuleDel(_)
+            | Self::Save
+            | Self::Secret(_)
+            | Self::Server(_)
+            | Self::Snapshot(_)
+            | Self::TxAllow(_)
+            | Self::TxBlock(_)
+            | Self::TxClear
+            | Self::TxEdit(_) => true,
+            Self::Auth(_)
+            | Self::BridgeInfo(_)
+            | Self::BusState(_)
+            | Self::ClearStats
+            | Self::Discover
+            | Self::Echo
+            | Self::End
+            | Self::Error(_)
+            | Self::FilterStats(_)
+            | Self::IdInfo(_)
+            | Self::LastFrame(_)
+            | Self::Lock
+            | Self::Magic(_)
+            | Self::NFilter(_)
+            | Self::Nonce(_)
+            | Self::PeerLatency(_)
+            | Self::PFilter(_)
+            | Self::Ping(_)
+            | Self::Pong(_)
+            | Self::ReceivedFrame(_)
+            | Self::RxDropped(_)
+            | Self::Sequence(_)
+            | Self::ShowCannelloni
+            | Self::ShowCyclic
+            | Self::ShowFilters
+            | Self::ShowGateway
+            | Self::ShowIds
+            | Self::ShowLast(_)
+            | Self::ShowPeer
+            | Self::ShowPeerLatency
+            | Self::ShowRtr
+            | Self::ShowRules
+            | Self::ShowServer
+            | Self::ShowTxInterlock
+            | Self::ShowTxQueue
+            | Self::Stream(_)
+            | Self::TxErr(_, _)
+            | Self::TxOk(_, _)
+            | Self::TxQueue(_) => false,
+        }
+    }
 }
 
 
@@ -135,6 +523,309 @@ mod tests {
         println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
         assert_eq!(ser.as_slice(), slice);
 
+        let slice = b"$fts,12a,3,1a2b3c,4711\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txok,4711,123456\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txerr,4711,BusOff\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$fts,12a,3,1a2b3c,,250\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$fts,1fffffff,88,0011223344556677,4294967295,4294967295,255\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txerr,4711,TxTimeout\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txerr,4711,Expired\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txallow,111_1110_****\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txblock,000_0000_****,d\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$udp,5000\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txedit,0\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txedit,2\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert_eq!(ComItem::deserialize(&mut deser).unwrap_err(), Error::ParseError);
+
+        let slice = b"$txint?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txq?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$txq,12\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$rxdrop,3\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$begin\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$ftsb,4294967295,1fffffff,88,0011223344556677,4294967295,4294967295,255\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$commit\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$abort\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$rtr,1fffffff,0011223344556677\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice =
+            b"$rule,15,4294967295,1_1000_1101_1010_1111_0001_0001_0000,********0011****,18daf110,%0%1%2%3%4%5%6%7\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$gw,7,0,1_1000_1101_1010_****_****_****_****,02**,1fffffff,0,ff,%1%0aa\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$gwdel,7\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$gw?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$peer,192.168.1.20,1234\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$peer\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$cannelloni,192.168.1.10,20000\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$peer?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$peerlat,12,5,50\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$peerlat?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$ping,4294967295\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$pong,0\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$server,10.0.0.1,4321\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$server\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$server?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$ruledel,15\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$rule?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$rtr?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$clearrtr\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$autorecover,100,10000\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$busrecover\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$bus,Recovering\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$bus,Sleeping\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        assert!(ComItem::deserialize(&mut deser).is_err());
+
         let slice = b"$err,EndNotFound\n";
         let mut deser = DeSer::<40>::from_slice(slice).unwrap();
         let item = ComItem::deserialize(&mut deser).unwrap();
@@ -163,6 +854,20 @@ mod tests {
         println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
         assert_eq!(ser.as_slice(), slice);
 
+        let slice = b"$clearstat\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$fstat,1200,1100,100\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
         let slice = b"$save\n";
         let mut deser = DeSer::<40>::from_slice(slice).unwrap();
         let item = ComItem::deserialize(&mut deser).unwrap();
@@ -170,6 +875,104 @@ mod tests {
         println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
         assert_eq!(ser.as_slice(), slice);
 
+        let slice = b"$lock\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$err,NotPermitted\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$secret,62656e63682d37\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$secret\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$nonce,00112233445566778899aabbccddeeff\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$auth,5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$snap,250\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$ids?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$id,1fffffff,88,0011223344556677,4294967295,4294967295\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$last?,12a\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$last,12a,3,1a2b3c\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$cyc,7,4294967295,1fffffff,88,0011223344556677\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$cycstop,7\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$cyc?\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
         let slice = b"$magic,67a35284e62a4b25\n";
         let mut deser = DeSer::<40>::from_slice(slice).unwrap();
         let item = ComItem::deserialize(&mut deser).unwrap();
@@ -184,11 +987,48 @@ mod tests {
         println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
         assert_eq!(ser.as_slice(), slice);
 
+        let slice = b"$nfilt,***_****_****,r\n";
+        let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+
         let slice = b"$pfilt,17,1_1111_0000_1111_0000_11*1_000*_1111\n";
         let mut deser = DeSer::<50>::from_slice(slice).unwrap();
         let item = ComItem::deserialize(&mut deser).unwrap();
         let ser = item.serialize();
         println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
         assert_eq!(ser.as_slice(), slice);
+
+        let slice = b"$pfilt,4294967295,1_1111_0000_1111_0000_11*1_000*_1111,r,8\n";
+        let mut deser = DeSer::<DATAGRAM_LEN>::from_slice(slice).unwrap();
+        let item = ComItem::deserialize(&mut deser).unwrap();
+        let ser = item.serialize();
+        println!("ComItem {}", str::from_utf8(ser.as_slice()).unwrap());
+        assert_eq!(ser.as_slice(), slice);
+    }
+
+    #[test]
+    fn requires_control() {
+        for slice in [
+            &b"$fts,12a,3,1a2b3c\n"[..],
+            b"$save\n",
+            b"$snap,250\n",
+            b"$peer\n",
+            b"$clearfilt\n",
+        ] {
+            let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+            assert!(ComItem::deserialize(&mut deser).unwrap().requires_control());
+        }
+        for slice in [
+            &b"$lock\n"[..],
+            b"$filt?\n",
+            b"$nfilt,1**_****_****\n",
+            b"$ping,1\n",
+        ] {
+            let mut deser = DeSer::<40>::from_slice(slice).unwrap();
+            assert!(!ComItem::deserialize(&mut deser).unwrap().requires_control());
+        }
     }
 }
@@ -36,6 +36,10 @@ impl<const CAP: usize> RxBuffer<CAP> {
         self.head - self.tail
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.head == self.tail
+    }
+
     pub fn set_head(&mut self, head: usize) {
         self.tail = 0;
         self.head = head;
@@ -135,9 +135,14 @@ pub trait DeSerialize {
     fn get_slice(&mut self) -> Result<&[u8], Error>;
     fn get_slice_hex(&mut self) -> Result<Vec8, Error>;
     fn get_u32(&mut self) -> Result<u32, Error>;
+    /// An empty field is read as None
+    fn get_u32_opt(&mut self) -> Result<Option<u32>, Error>;
     fn get_u32_hex(&mut self) -> Result<u32, Error>;
+    fn has_next(&self) -> bool;
     fn is_end(&self) -> bool;
     fn push(&mut self, b: u8) -> Result<(), Error>;
+    /// Skips `prefix` if the next field starts with it
+    fn skip_prefix(&mut self, prefix: u8) -> bool;
 }
 
 pub struct DeSer<const CAP: usize> {
@@ -251,6 +256,16 @@ impl<const CAP: usize> DeSerialize for DeSer<CAP> {
         Ok(r)
     }
 
+    fn get_u32_opt(&mut self) -> Result<Option<u32>, Error> {
+        match self.vec.get(self.head + 1) {
+            Some(b',') | Some(b'\n') => {
+                self.get_slice()?;
+                Ok(None)
+            }
+            _ => self.get_u32().map(Some),
+        }
+    }
+
     fn get_u32_hex(&mut self) -> Result<u32, Error> {
         let slice = &self.get_slice()?[1..];
         let mut r = 0_u32;
@@ -265,6 +280,10 @@ impl<const CAP: usize> DeSerialize for DeSer<CAP> {
         Ok(r)
     }
 
+    fn has_next(&self) -> bool {
+        !self.is_end && self.head + 1 < self.vec.len()
+    }
+
     fn is_end(&self) -> bool {
         self.is_end
     }
@@ -272,14 +291,23 @@ impl<const CAP: usize> DeSerialize for DeSer<CAP> {
     fn push(&mut self, b: u8) -> Result<(), Error> {
         self.vec.push(b).map_err(|_| Error::BufIsFull)
     }
+
+    fn skip_prefix(&mut self, prefix: u8) -> bool {
+        if self.vec.get(self.head + 1) == Some(&prefix) {
+            self.head += 1;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
     extern crate std;
-    use core::u32;
     use std::println;
 
     #[test]
@@ -290,8 +318,15 @@ mod tests {
         assert_eq!(de_ser.is_end, false);
         assert_eq!(de_ser.get_slice().unwrap(), b",456");
         assert_eq!(de_ser.is_end, false);
+        assert_eq!(de_ser.has_next(), true);
         assert_eq!(de_ser.get_slice().unwrap(), b",789");
         assert_eq!(de_ser.is_end, true);
+        assert_eq!(de_ser.has_next(), false);
+
+        let mut de_ser = DeSer::<40>::default();
+        de_ser.extend_from_slice(b",123,").unwrap();
+        assert_eq!(de_ser.get_slice().unwrap(), b",123");
+        assert_eq!(de_ser.has_next(), false);
 
         let mut de_ser = DeSer::<40>::default();
         de_ser
@@ -324,6 +359,13 @@ mod tests {
         de_ser.extend_from_slice(b",a2,\n").unwrap();
         assert_eq!(de_ser.get_slice_hex().unwrap().as_slice(), b"\xa2");
         assert_eq!(de_ser.get_slice_hex().unwrap().as_slice(), b"");
+
+        let mut de_ser = DeSer::<40>::default();
+        de_ser.extend_from_slice(b",,17,\n").unwrap();
+        assert_eq!(de_ser.get_u32_opt(), Ok(None));
+        assert_eq!(de_ser.get_u32_opt(), Ok(Some(17)));
+        assert_eq!(de_ser.get_u32_opt(), Ok(None));
+        assert_eq!(de_ser.is_end, true);
     }
 
     #[test]
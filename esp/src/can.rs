@@ -1,19 +1,51 @@
+use core::{
+    cell::{Cell, RefCell},
+    future::pending,
+};
+
 use embedded_can::Frame;
 
-use embassy_futures::select::{select3, Either3};
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, watch::Receiver};
+use embassy_futures::select::{select, select4, Either, Either4};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+    watch::Receiver,
+};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
 
 use esp_alloc as _;
 use esp_backtrace as _;
 use esp_hal::{
-    twai::{EspTwaiFrame, TimingConfig, Twai},
+    twai::{EspTwaiError, EspTwaiFrame, TimingConfig, Twai},
     Async,
 };
 use log::{error, info};
 
-use crate::ComChannel;
+use crate::{wifi::Target, CanChannel, CmdChannel};
 use corelib::*;
 
+const TX_QUEUE_SIZE: usize = 32;
+const BATCH_SIZE: usize = 64;
+const RTR_SIZE: usize = 32;
+/// Back-off of the automatic bus-off recovery until configured otherwise
+pub const RECOVERY_BACKOFF: Backoff = Backoff::new(100, 10_000);
+/// Time for the recovery sequence of 128 * 11 recessive bits, even at 10 kbit/s
+const RECOVERY_CHECK: Duration = Duration::from_millis(250);
+
+/// Bitrate in bit/s of the timing names of `timing_config`
+pub fn bitrate(timing: &str) -> u32 {
+    match timing {
+        "B10K" => 10_000,
+        "B20K" => 20_000,
+        "B50K" => 50_000,
+        "B100K" => 100_000,
+        "B125K" => 125_000,
+        "B250K" => 250_000,
+        "B500K" => 500_000,
+        _ => 1_000_000, // "B1000K"
+    }
+}
+
 pub fn timing_config(timing: &str) -> TimingConfig {
     let baud_rate_prescaler: u16 = match timing {
         "B10K" => 400,
@@ -34,57 +66,351 @@ pub fn timing_config(timing: &str) -> TimingConfig {
     }
 }
 
+pub type CanTxBatch = TxBatch<BATCH_SIZE>;
+
+/// Received frames dropped because the main loop fell behind
+static RX_DROPPED: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+/// Number of received frames dropped since the start, wraps around at 2^32
+pub fn rx_dropped() -> u32 {
+    RX_DROPPED.lock(|dropped| dropped.get())
+}
+
+/// Transmit queue and batch shared by the main loop and the can task
+pub struct CanTxQueue {
+    queue: Mutex<CriticalSectionRawMutex, RefCell<TxQueue<TX_QUEUE_SIZE>>>,
+    batch: Mutex<CriticalSectionRawMutex, RefCell<CanTxBatch>>,
+    signal: Signal<CriticalSectionRawMutex, ()>,
+    abort: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Default for CanTxQueue {
+    fn default() -> Self {
+        Self {
+            queue: Mutex::new(RefCell::new(TxQueue::default())),
+            batch: Mutex::new(RefCell::new(CanTxBatch::default())),
+            signal: Signal::new(),
+            abort: Signal::new(),
+        }
+    }
+}
+
+impl CanTxQueue {
+    pub fn push(&self, tx_frame: TxFrame) -> Result<(), Error> {
+        self.queue
+            .lock(|queue| queue.borrow_mut().push(tx_frame, Instant::now()))?;
+        self.signal.signal(());
+        Ok(())
+    }
+
+    /// Hands a batch over to the can task unless the previous batch is still transmitted
+    pub fn commit(&self, batch: CanTxBatch) -> Result<(), Error> {
+        self.batch.lock(|current| {
+            let mut current = current.borrow_mut();
+            if !current.is_empty() {
+                return Err(Error::BufIsFull);
+            }
+            *current = batch;
+            Ok(())
+        })?;
+        self.signal.signal(());
+        Ok(())
+    }
+
+    /// Discards the committed batch, a delay of the batch still waited for ends at once
+    pub fn abort_batch(&self) {
+        self.batch.lock(|batch| batch.borrow_mut().clear());
+        self.abort.signal(());
+    }
+
+    pub fn depth(&self) -> usize {
+        self.queue.lock(|queue| queue.borrow().len())
+    }
+
+    fn pop(&self) -> Option<TxFrame> {
+        self.queue.lock(|queue| queue.borrow_mut().pop())
+    }
+
+    fn pop_expired(&self) -> Option<TxFrame> {
+        self.queue
+            .lock(|queue| queue.borrow_mut().pop_expired(Instant::now()))
+    }
+
+    fn next_batch_delay(&self) -> Option<u32> {
+        self.batch.lock(|batch| batch.borrow().next_delay())
+    }
+
+    fn pop_batch(&self) -> Option<TxFrame> {
+        self.batch.lock(|batch| batch.borrow_mut().pop())
+    }
+
+    async fn wait(&self) {
+        while self.queue.lock(|queue| queue.borrow().is_empty())
+            && self.batch.lock(|batch| batch.borrow().is_empty())
+        {
+            self.signal.wait().await;
+        }
+    }
+}
+
+/// Sends an item to the hosts, items are dropped while no host is connected
+fn report(wifi_tx_channel: &CanChannel, is_connected: bool, target: Target, item: ComItem) {
+    if is_connected && wifi_tx_channel.try_send((target, item)).is_err() {
+        error!("Can Queue");
+    }
+}
+
+/// Session that gets the transmission results of a frame
+fn origin(tx_frame: &TxFrame) -> Target {
+    tx_frame.origin().map_or(Target::All, Target::Session)
+}
+
+pub type CanRtrResponders = RtrResponders<RTR_SIZE>;
+
+/// Responses to remote frames shared by the main loop and the can task
+pub struct RtrTable {
+    responders: Mutex<CriticalSectionRawMutex, RefCell<CanRtrResponders>>,
+}
+
+impl Default for RtrTable {
+    fn default() -> Self {
+        Self {
+            responders: Mutex::new(RefCell::new(CanRtrResponders::default())),
+        }
+    }
+}
+
+impl RtrTable {
+    pub fn set(&self, response: RtrResponse) -> Result<(), Error> {
+        self.responders
+            .lock(|responders| responders.borrow_mut().set(response))
+    }
 
+    pub fn clear(&self) {
+        self.responders
+            .lock(|responders| responders.borrow_mut().clear());
+    }
+
+    /// Removes the responses for which `keep` returns false
+    pub fn retain(&self, keep: impl FnMut(&RtrResponse) -> bool) {
+        self.responders
+            .lock(|responders| responders.borrow_mut().retain(keep));
+    }
+
+    /// Returns a copy of the table for listing and saving
+    pub fn responders(&self) -> CanRtrResponders {
+        self.responders.lock(|responders| responders.borrow().clone())
+    }
+
+    fn respond(&self, frame: &CanFrame) -> Option<CanFrame> {
+        self.responders
+            .lock(|responders| responders.borrow().respond(frame))
+    }
+}
 
 #[embassy_executor::task]
 pub async fn comm(
     mut twai: Twai<'static, Async>,
-    wifi_tx_channel: &'static ComChannel,
-    can_tx_channel: &'static ComChannel,
+    wifi_tx_channel: &'static CanChannel,
+    can_tx_queue: &'static CanTxQueue,
+    can_cmd_channel: &'static CmdChannel,
+    rtr_table: &'static RtrTable,
     mut connection: Receiver<'static, CriticalSectionRawMutex, bool, 1>,
 ) {
     info!("start can receive");
     let mut is_connected = false;
+    let mut bus_state = BusState::Active;
+    let mut auto_recover = RECOVERY_BACKOFF;
+    // next recovery step while the controller is not active
+    let mut recover_at: Option<Instant> = None;
+    // transmission time of the next frame of a batch
+    let mut batch_at: Option<Instant> = None;
     loop {
         let conn = async { connection.changed().await };
         let rx_frame = async { twai.receive_async().await };
-        let tx_frame = async { can_tx_channel.receive().await };
+        let tx_ready = async {
+            match batch_at {
+                Some(instant) => {
+                    select(Timer::at(instant), can_tx_queue.abort.wait()).await;
+                }
+                None => can_tx_queue.wait().await,
+            }
+        };
+        let cmd = async { can_cmd_channel.receive().await };
+        let recovery = async {
+            match recover_at {
+                Some(instant) => Timer::at(instant).await,
+                None => pending::<()>().await,
+            }
+        };
 
-        match select3(conn, rx_frame, tx_frame).await {
-            Either3::First(connected) => {
+        let mut bus_off = false;
+        match select4(conn, rx_frame, tx_ready, select(cmd, recovery)).await {
+            Either4::First(connected) => {
                 is_connected = connected;
             }
-            Either3::Second(rx_frame) => {
+            Either4::Second(rx_frame) => {
                 let frame = match rx_frame {
                     Err(_) => {
                         error!("Got can bus error");
+                        bus_off = twai.is_bus_off();
+                        if !bus_off {
+                            continue;
+                        }
+                        None
+                    }
+                    Ok(esp_frame) => Some(CanFrame::from_frame(esp_frame)),
+                };
+                // remote frames are answered without a round trip to the host
+                let response = frame.as_ref().and_then(|frame| rtr_table.respond(frame));
+                if let Some(response) = response {
+                    if can_tx_queue.push(TxFrame::new(response)).is_err() {
+                        error!("Can Tx Queue");
+                    }
+                }
+                // the main loop also needs frames without a host for rules and the peer
+                if let Some(frame) = frame {
+                    // a busy bus must not restart the bridge, the frame is dropped and counted
+                    if wifi_tx_channel
+                        .try_send((Target::All, ComItem::ReceivedFrame(frame)))
+                        .is_err()
+                    {
+                        RX_DROPPED.lock(|dropped| dropped.set(dropped.get().wrapping_add(1)));
+                    }
+                }
+            }
+            Either4::Third(()) => {
+                // the frames of a batch are sent without other frames in between
+                let batch_frame = match (batch_at.take(), can_tx_queue.next_batch_delay()) {
+                    (Some(_), _) | (None, Some(0)) => can_tx_queue.pop_batch(),
+                    (None, Some(delay)) => {
+                        can_tx_queue.abort.reset();
+                        batch_at = Some(Instant::now() + Duration::from_millis(delay as u64));
                         continue;
                     }
-                    Ok(esp_frame) => CanFrame::from_frame(esp_frame),
+                    (None, None) => None,
                 };
-                if is_connected {
-                    match wifi_tx_channel.try_send(ComItem::ReceivedFrame(frame)) {
-                        Ok(()) => (),
-                        Err(_) => {
-                            error!("Can Queue");
-                            esp_hal::system::software_reset();
+                let tx_frame = match batch_frame {
+                    Some(tx_frame) => tx_frame,
+                    None => {
+                        // stale frames are dropped before the next frame is sent
+                        while let Some(tx_frame) = can_tx_queue.pop_expired() {
+                            let item = match tx_frame.tag() {
+                                Some(tag) => ComItem::TxErr(tag, Error::Expired),
+                                None => ComItem::Error(Error::Expired),
+                            };
+                            report(wifi_tx_channel, is_connected, origin(&tx_frame), item);
                         }
+                        let Some(tx_frame) = can_tx_queue.pop() else {
+                            continue;
+                        };
+                        tx_frame
                     }
+                };
+                let can_frame = tx_frame.frame();
+                let frame = if can_frame.is_remote_frame() {
+                    EspTwaiFrame::new_remote(can_frame.id(), can_frame.dlc()).unwrap()
+                } else {
+                    EspTwaiFrame::new(can_frame.id(), can_frame.data()).unwrap()
+                };
+                // queued frames are discarded until the controller is active again
+                let mut result = Err(Error::BusOff);
+                if bus_state == BusState::Active {
+                    // the controller retransmits the frame until it is acknowledged, dropping
+                    // the transmit future at the timeout sets the abort bit of the controller,
+                    // a frame already on the bus is completed but not repeated
+                    let transmit = twai.transmit_async(&frame);
+                    let transmitted = match tx_frame.timeout() {
+                        Some(timeout) => {
+                            let timeout = Duration::from_millis(timeout as u64);
+                            with_timeout(timeout, transmit).await
+                        }
+                        None => Ok(transmit.await),
+                    };
+                    result = match transmitted {
+                        Ok(Ok(())) => Ok(()),
+                        Ok(Err(EspTwaiError::BusOff)) => Err(Error::BusOff),
+                        Ok(Err(_)) => Err(Error::TxFailed),
+                        Err(_) => Err(Error::TxTimeout),
+                    };
+                    bus_off = result == Err(Error::BusOff) || twai.is_bus_off();
+                }
+                if result.is_err() {
+                    error!("Could not send can frame");
+                }
+                if let Some(tag) = tx_frame.tag() {
+                    let item = match result {
+                        Ok(()) => ComItem::TxOk(tag, Instant::now().as_millis() as u32),
+                        Err(error) => ComItem::TxErr(tag, error),
+                    };
+                    report(wifi_tx_channel, is_connected, origin(&tx_frame), item);
                 }
             }
-            Either3::Third(tx_frame) => {
-                if let ComItem::FrameToSend(can_frame) = tx_frame {
-                    let frame = if can_frame.is_remote_frame() {
-                        EspTwaiFrame::new_remote(can_frame.id(), can_frame.dlc()).unwrap()
+            Either4::Fourth(Either::First(cmd)) => match cmd {
+                ComItem::AutoRecover(backoff) => {
+                    auto_recover = backoff;
+                    if bus_state == BusState::BusOff {
+                        recover_at = auto_recover
+                            .next_delay()
+                            .map(|delay| Instant::now() + Duration::from_millis(delay as u64));
+                    }
+                }
+                ComItem::BusRecover => {
+                    if bus_state == BusState::BusOff {
+                        recover_at = Some(Instant::now());
                     } else {
-                        EspTwaiFrame::new(can_frame.id(), can_frame.data()).unwrap()
-                    };
-                    match twai.transmit_async(&frame).await {
-                        Ok(()) => (),
-                        Err(_) => error!("Could not send can frame"),
+                        report(
+                            wifi_tx_channel,
+                            is_connected,
+                            Target::All,
+                            ComItem::BusState(bus_state),
+                        );
                     }
                 }
+                _ => (),
+            },
+            Either4::Fourth(Either::Second(())) => {
+                if bus_state == BusState::BusOff {
+                    info!("recover from bus-off");
+                    // leaving the reset mode starts the bus-off recovery sequence
+                    twai = twai.stop().start();
+                    bus_state = BusState::Recovering;
+                    recover_at = Some(Instant::now() + RECOVERY_CHECK);
+                    report(
+                        wifi_tx_channel,
+                        is_connected,
+                        Target::All,
+                        ComItem::BusState(bus_state),
+                    );
+                } else if twai.is_bus_off() {
+                    bus_off = true;
+                } else {
+                    info!("bus-off recovery finished");
+                    auto_recover.reset();
+                    bus_state = BusState::Active;
+                    recover_at = None;
+                    report(
+                        wifi_tx_channel,
+                        is_connected,
+                        Target::All,
+                        ComItem::BusState(bus_state),
+                    );
+                }
             }
         };
+
+        if bus_off && bus_state != BusState::BusOff {
+            error!("can controller is bus-off");
+            bus_state = BusState::BusOff;
+            recover_at = auto_recover
+                .next_delay()
+                .map(|delay| Instant::now() + Duration::from_millis(delay as u64));
+            report(
+                wifi_tx_channel,
+                is_connected,
+                Target::All,
+                ComItem::BusState(bus_state),
+            );
+        }
     }
 }
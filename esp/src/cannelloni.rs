@@ -0,0 +1,123 @@
+use core::future::pending;
+
+use embassy_futures::select::{select4, Either4};
+use embassy_net::{
+    udp::{PacketMetadata, UdpSocket},
+    IpEndpoint, Stack,
+};
+use embassy_time::{Duration, Instant, Timer};
+
+use esp_alloc as _;
+use esp_backtrace as _;
+
+use log::{info, warn};
+
+use crate::{wifi::ip_endpoint, ComChannel, EndpointWatch};
+use corelib::{
+    cannelloni_decode, CannelloniEncoder, ComItem, CANNELLONI_PACKET_LEN, CANNELLONI_PORT,
+};
+
+/// Time the first frame of a packet waits for further frames
+const CANNELLONI_DELAY: Duration = Duration::from_millis(10);
+/// Frames of one received packet, further frames are dropped
+const CANNELLONI_FRAMES: usize = 64;
+/// Maximum length of a received packet, a Linux peer fills packets up to the MTU
+const RECEIVE_LEN: usize = 1500;
+
+/// Exchanges frames with a cannelloni peer, e.g. a Linux host attached to a `vcan` interface
+///
+/// The bridge listens on the cannelloni port and sends its frames to the configured endpoint.
+/// Packets from other addresses are ignored. Frames are dropped while no peer is configured.
+#[embassy_executor::task]
+pub async fn comm(
+    stack: Stack<'static>,
+    cannelloni_endpoint: &'static EndpointWatch,
+    cannelloni_rx_channel: &'static ComChannel,
+    cannelloni_tx_channel: &'static ComChannel,
+) {
+    let rx_meta = mk_static!([PacketMetadata; 4], [PacketMetadata::EMPTY; 4]);
+    let rx_buffer = mk_static!([u8; 4 * RECEIVE_LEN], [0; 4 * RECEIVE_LEN]);
+    let tx_meta = mk_static!([PacketMetadata; 4], [PacketMetadata::EMPTY; 4]);
+    let tx_buffer = mk_static!([u8; 2048], [0; 2048]);
+    let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+    if let Err(e) = socket.bind(CANNELLONI_PORT) {
+        warn!("cannelloni bind error: {e:?}");
+        return;
+    }
+    let mut endpoint_rx = cannelloni_endpoint.receiver().unwrap();
+    let mut encoder = CannelloniEncoder::<CANNELLONI_PACKET_LEN>::default();
+    let mut deadline = Instant::now();
+    let mut packet = [0; RECEIVE_LEN];
+
+    loop {
+        let Some(endpoint) = endpoint_rx.get().await else {
+            endpoint_rx.changed().await;
+            while cannelloni_tx_channel.try_receive().is_ok() {}
+            continue;
+        };
+        let remote = ip_endpoint(&endpoint);
+        info!("Cannelloni peer {:?}", remote);
+
+        loop {
+            let item = async { cannelloni_tx_channel.receive().await };
+            let read = async { socket.recv_from(&mut packet).await };
+            let pending_deadline = (!encoder.is_empty()).then_some(deadline);
+            let timeout = async move {
+                match pending_deadline {
+                    Some(deadline) => Timer::at(deadline).await,
+                    None => pending().await,
+                }
+            };
+
+            match select4(item, read, timeout, endpoint_rx.changed()).await {
+                Either4::First(ComItem::ReceivedFrame(frame)) => {
+                    let mut first = encoder.is_empty();
+                    if encoder.push(&frame).is_err() {
+                        flush(&socket, &mut encoder, remote).await;
+                        // an empty packet has space for a frame
+                        let _ = encoder.push(&frame);
+                        first = true;
+                    }
+                    if first {
+                        deadline = Instant::now() + CANNELLONI_DELAY;
+                    }
+                }
+                Either4::First(_) => (),
+                Either4::Second(Ok((n, meta))) => {
+                    if meta.endpoint.addr != remote.addr {
+                        continue;
+                    }
+                    match cannelloni_decode::<CANNELLONI_FRAMES>(&packet[..n]) {
+                        Ok(frames) => {
+                            for frame in frames {
+                                let item = ComItem::ReceivedFrame(frame);
+                                cannelloni_rx_channel.send(item).await;
+                            }
+                        }
+                        Err(error) => warn!("cannelloni peer sent invalid packet: {error:?}"),
+                    }
+                }
+                Either4::Second(Err(e)) => warn!("cannelloni receive error: {e:?}"),
+                Either4::Third(()) => flush(&socket, &mut encoder, remote).await,
+                // frames collected for the old peer are dropped
+                Either4::Fourth(_) => {
+                    let _ = encoder.take();
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Sends the collected frames, a packet that cannot be sent is dropped
+async fn flush(
+    socket: &UdpSocket<'_>,
+    encoder: &mut CannelloniEncoder<CANNELLONI_PACKET_LEN>,
+    remote: IpEndpoint,
+) {
+    if let Some(packet) = encoder.take() {
+        if let Err(e) = socket.send_to(packet, remote).await {
+            warn!("cannelloni send error: {e:?}");
+        }
+    }
+}
@@ -4,25 +4,42 @@ use embedded_storage::{ReadStorage, Storage};
 use esp_bootloader_esp_idf::partitions::{
     read_partition_table, AppPartitionSubType, DataPartitionSubType, PartitionType, PARTITION_TABLE_MAX_LEN
 };
-use esp_println::print;
+use esp_println::{print, println};
 use esp_storage::FlashStorage;
 
 use corelib::*;
 use log::{info, error};
-use crate::init::ComChannel;
+use crate::{
+    init::SessionChannel,
+    wifi::{SessionMessage, Target},
+};
+
+/// A config with all filters, interlock entries, cyclic jobs, remote frame responses and rules
+/// using 29 bit patterns and data masks takes about 7 kB, a larger config is refused with
+/// BufIsFull
+const CONF_BUFFER_SIZE: usize = 8192;
 
-const CONF_BUFFER_SIZE: usize = 128;
+/// The config buffer is static, it is too large for the stack of load and save
+pub type ConfBuffer = RxBuffer<CONF_BUFFER_SIZE>;
 
 pub struct Config {
     flash: FlashStorage,
+    buf: &'static mut ConfBuffer,
 }
 
 impl Config {
-    pub fn new(flash: FlashStorage) -> Self {
-        Self { flash }
+    pub fn new(flash: FlashStorage, buf: &'static mut ConfBuffer) -> Self {
+        Self { flash, buf }
+    }
+
+    /// Starts a new config with the magic datagram
+    pub fn buffer(&mut self) -> ConfigBuffer<'_> {
+        self.buf.en_mut_block().fill(0);
+        self.buf.write(&ComItem::Magic(true).serialize()).unwrap();
+        ConfigBuffer { config: self }
     }
 
-    pub async fn load(&mut self, wifi_rx_channel: &'static ComChannel) {
+    pub async fn load(&mut self, wifi_rx_channel: &'static SessionChannel) {
         let mut pt_mem = [0u8; PARTITION_TABLE_MAX_LEN];
         let pt = read_partition_table(&mut self.flash, &mut pt_mem).unwrap();
 
@@ -35,14 +52,14 @@ impl Config {
             .read(32, &mut app_desc)
             .unwrap();
 
-        let mut buf = RxBuffer::<CONF_BUFFER_SIZE>::default();
+        let buf = &mut *self.buf;
         let nvs = pt
             .find_partition(PartitionType::Data(DataPartitionSubType::Nvs))
             .unwrap()
             .unwrap();
         let mut nvs_partition = nvs.as_embedded_storage(&mut self.flash);
         nvs_partition
-            .read(0, &mut buf.en_mut_block())
+            .read(0, buf.en_mut_block())
             .unwrap();
 
         buf.set_head(CONF_BUFFER_SIZE);
@@ -51,7 +68,7 @@ impl Config {
         let mut go_on = true;
         let mut magic_detected = false;
         while go_on {
-            let mut de_ser = DeSer::<50>::default();
+            let mut de_ser = DeSer::<DATAGRAM_LEN>::default();
             match buf.read(&mut de_ser) {
                 Ok(()) => (),
                 Err(_) => break,
@@ -63,15 +80,15 @@ impl Config {
             }
 
             if magic_detected {
-                print!("  {}", str::from_utf8(de_ser.as_slice()).unwrap());
+                print_line(de_ser.as_slice());
                 if let Ok(item) = ComItem::deserialize(&mut de_ser) {
-                    wifi_rx_channel.send(item).await;
+                    wifi_rx_channel.send(SessionMessage::Item(Target::All, item)).await;
                 };
             }
         }
     }
 
-    pub fn write(&mut self, tx_buf: &mut RxBuffer<CONF_BUFFER_SIZE>) {
+    fn write(&mut self) {
         let mut pt_mem = [0u8; PARTITION_TABLE_MAX_LEN];
         let pt = read_partition_table(&mut self.flash, &mut pt_mem).unwrap();
 
@@ -91,36 +108,38 @@ impl Config {
         let mut nvs_partition = nvs.as_embedded_storage(&mut self.flash);
 
         info!("Config write");
-        print!("{}", str::from_utf8(&tx_buf.en_mut_block()).unwrap());
-        match nvs_partition.write(0, &tx_buf.en_mut_block()) {
+        for line in self.buf.as_slice().split_inclusive(|b| *b == b'\n') {
+            print_line(line);
+        }
+        match nvs_partition.write(0, self.buf.en_mut_block()) {
             Ok(()) => (),
             Err(e) => error!("{:?}", e),
         }
     }
 }
 
-pub struct ConfigBuffer {
-    buf: RxBuffer<CONF_BUFFER_SIZE>,
-}
-
-impl Default for ConfigBuffer {
-    fn default() -> Self {
-        let mut buf = RxBuffer::<CONF_BUFFER_SIZE>::default();
-        buf.write(&ComItem::Magic(true).serialize()).unwrap();
-        ConfigBuffer { buf }
+/// Prints a line of the config, the shared secret is never shown
+fn print_line(line: &[u8]) {
+    if line.starts_with(b"$secret,") {
+        println!("  $secret,<redacted>");
+    } else {
+        print!("  {}", str::from_utf8(line).unwrap_or_default());
     }
+}
 
+pub struct ConfigBuffer<'a> {
+    config: &'a mut Config,
 }
 
-impl ConfigBuffer {
+impl ConfigBuffer<'_> {
     pub fn add_item(&mut self, item: &ComItem) -> Result<(), Error> {
-        self.buf.write(&item.serialize())?;
+        self.config.buf.write(&item.serialize())?;
         Ok(())
     }
 
-    pub fn finish(&mut self, config: &mut Config) -> Result<(), Error> {
-        self.buf.write(&ComItem::End.serialize())?;
-        config.write(&mut self.buf);
+    pub fn finish(self) -> Result<(), Error> {
+        self.config.buf.write(&ComItem::End.serialize())?;
+        self.config.write();
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+use embassy_net::{
+    udp::{PacketMetadata, UdpSocket},
+    Stack,
+};
+use esp_hal::efuse::Efuse;
+use heapless::String;
+
+use esp_alloc as _;
+use esp_backtrace as _;
+
+use log::{info, warn};
+
+use crate::wifi::SESSION_PORT;
+use corelib::{
+    bridge_name, BridgeInfo, ComItem, DeSer, Endpoint, Serialize, DATAGRAM_LEN, DISCOVERY_PORT,
+    NAME_LEN,
+};
+
+/// Name of the bridge derived from the MAC address
+pub fn name() -> String<NAME_LEN> {
+    bridge_name(&Efuse::mac_address())
+}
+
+/// Answers the discovery queries broadcast by the hosts
+#[embassy_executor::task]
+pub async fn comm(stack: Stack<'static>, bitrate: u32) {
+    let rx_meta = mk_static!([PacketMetadata; 2], [PacketMetadata::EMPTY; 2]);
+    let rx_buffer = mk_static!([u8; 256], [0; 256]);
+    let tx_meta = mk_static!([PacketMetadata; 2], [PacketMetadata::EMPTY; 2]);
+    let tx_buffer = mk_static!([u8; 256], [0; 256]);
+    let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+    if let Err(e) = socket.bind(DISCOVERY_PORT) {
+        warn!("discovery bind error: {e:?}");
+        return;
+    }
+    let name = name();
+    info!("Discoverable as {name}");
+    let mut query = [0; DATAGRAM_LEN];
+
+    loop {
+        let (n, meta) = match socket.recv_from(&mut query).await {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("discovery receive error: {e:?}");
+                continue;
+            }
+        };
+        let Ok(mut de_ser) = DeSer::<DATAGRAM_LEN>::from_slice(&query[..n]) else {
+            continue;
+        };
+        if !matches!(ComItem::deserialize(&mut de_ser), Ok(ComItem::Discover)) {
+            continue;
+        }
+        let Some(config) = stack.config_v4() else {
+            continue;
+        };
+        let endpoint = Endpoint::new(config.address.address().octets(), SESSION_PORT);
+        let version = env!("CARGO_PKG_VERSION");
+        // the name and the version are valid texts
+        let Ok(info) = BridgeInfo::new(&name, endpoint, version, bitrate) else {
+            continue;
+        };
+        let ser = ComItem::BridgeInfo(info).serialize();
+        if let Err(e) = socket.send_to(ser.as_slice(), meta.endpoint).await {
+            warn!("discovery send error: {e:?}");
+        }
+    }
+}
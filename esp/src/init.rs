@@ -1,7 +1,13 @@
+use core::cell::Cell;
+
 use embassy_net::{Runner, Stack, StackResources};
 use embassy_sync::{
-    blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex},
+    blocking_mutex::{
+        raw::{CriticalSectionRawMutex, NoopRawMutex},
+        Mutex,
+    },
     channel::Channel,
+    pubsub::{Publisher, PubSubChannel, Subscriber},
     watch::{Receiver, Sender, Watch},
 };
 
@@ -20,10 +26,28 @@ use esp_radio::{
 use esp_storage::FlashStorage;
 
 use corelib::*;
-use crate::{can::timing_config, config::Config};
+use crate::{
+    can::{timing_config, CanTxQueue, RtrTable},
+    config::{ConfBuffer, Config},
+    wifi::{SessionMessage, Sessions, SocketBuffers, Target, MAX_SESSIONS},
+};
 
 pub type ComChannel = Channel<NoopRawMutex, ComItem, 128>;
-const CAN_BAUDRATE: &str = env!("CAN_BAUDRATE");
+/// Items of the can task, transmission results go to the session that queued the frame
+pub type CanChannel = Channel<NoopRawMutex, (Target, ComItem), 128>;
+pub type SessionChannel = Channel<NoopRawMutex, SessionMessage, 128>;
+const HOST_QUEUE: usize = 128;
+/// Items for the hosts, each session picks the items for its target
+pub type HostChannel = PubSubChannel<NoopRawMutex, (Target, ComItem), HOST_QUEUE, MAX_SESSIONS, 1>;
+pub type HostPublisher =
+    Publisher<'static, NoopRawMutex, (Target, ComItem), HOST_QUEUE, MAX_SESSIONS, 1>;
+pub type HostSubscriber =
+    Subscriber<'static, NoopRawMutex, (Target, ComItem), HOST_QUEUE, MAX_SESSIONS, 1>;
+pub type CmdChannel = Channel<NoopRawMutex, ComItem, 4>;
+pub type EndpointWatch = Watch<CriticalSectionRawMutex, Option<Endpoint>, 1>;
+/// Shared secret of the sessions, no secret disables the authentication
+pub type SecretCell = Mutex<CriticalSectionRawMutex, Cell<Option<Secret>>>;
+pub const CAN_BAUDRATE: &str = env!("CAN_BAUDRATE");
 
 #[allow(clippy::type_complexity)]
 pub fn init() -> (
@@ -31,12 +55,22 @@ pub fn init() -> (
     Stack<'static>,
     WifiController<'static>,
     Twai<'static, Async>,
+    &'static CanChannel,
+    &'static CanTxQueue,
+    &'static CmdChannel,
+    &'static RtrTable,
+    &'static SessionChannel,
+    &'static HostChannel,
     &'static ComChannel,
     &'static ComChannel,
+    &'static EndpointWatch,
+    &'static EndpointWatch,
     &'static ComChannel,
-    &'static ComChannel,
+    &'static EndpointWatch,
     Receiver<'static, CriticalSectionRawMutex, bool, 1>,
-    Sender<'static, CriticalSectionRawMutex, bool, 1>,
+    &'static Sessions,
+    &'static mut [SocketBuffers; MAX_SESSIONS],
+    &'static SecretCell,
     Config,
 ) {
     esp_println::logger::init_logger_from_env();
@@ -67,7 +101,7 @@ pub fn init() -> (
     let (stack, runner) = embassy_net::new(
         wifi_interface,
         config,
-        mk_static!(StackResources<3>, StackResources::<3>::new()),
+        mk_static!(StackResources<14>, StackResources::<14>::new()),
         seed,
     );
 
@@ -84,18 +118,32 @@ pub fn init() -> (
     ).into_async();
     let twai: Twai<'_, Async> = twai_config.start();
 
-    let can_rx_channel = &*mk_static!(ComChannel, ComChannel::new());
-    let can_tx_channel = &*mk_static!(ComChannel, ComChannel::new());
-    let wifi_rx_channel = &*mk_static!(ComChannel, ComChannel::new());
-    let wifi_tx_channel = &*mk_static!(ComChannel, ComChannel::new());
+    let can_rx_channel = &*mk_static!(CanChannel, CanChannel::new());
+    let can_tx_queue = &*mk_static!(CanTxQueue, CanTxQueue::default());
+    let can_cmd_channel = &*mk_static!(CmdChannel, CmdChannel::new());
+    let rtr_table = &*mk_static!(RtrTable, RtrTable::default());
+    let wifi_rx_channel = &*mk_static!(SessionChannel, SessionChannel::new());
+    let host_channel = &*mk_static!(HostChannel, HostChannel::new());
+    let peer_rx_channel = &*mk_static!(ComChannel, ComChannel::new());
+    let peer_tx_channel = &*mk_static!(ComChannel, ComChannel::new());
+    let peer_endpoint = &*mk_static!(EndpointWatch, EndpointWatch::new());
+    let server_endpoint = &*mk_static!(EndpointWatch, EndpointWatch::new());
+    let cannelloni_tx_channel = &*mk_static!(ComChannel, ComChannel::new());
+    let cannelloni_endpoint = &*mk_static!(EndpointWatch, EndpointWatch::new());
 
     static SIGNAL_CONN: Watch<CriticalSectionRawMutex, bool, 1> = Watch::new();
     let signal_conn_rx: Receiver<'static, CriticalSectionRawMutex, bool, 1> =
         SIGNAL_CONN.receiver().unwrap();
     let signal_conn_tx: Sender<'static, CriticalSectionRawMutex, bool, 1> = SIGNAL_CONN.sender();
+    let sessions = &*mk_static!(Sessions, Sessions::new(signal_conn_tx));
+    let secret = &*mk_static!(SecretCell, Mutex::new(Cell::new(None)));
+    let socket_buffers = mk_static!(
+        [SocketBuffers; MAX_SESSIONS],
+        [SocketBuffers::new(); MAX_SESSIONS]
+    );
 
     let flash = FlashStorage::new();
-    let config = Config::new(flash);
+    let config = Config::new(flash, mk_static!(ConfBuffer, ConfBuffer::default()));
 
 
     (
@@ -104,11 +152,21 @@ pub fn init() -> (
         controller,
         twai,
         can_rx_channel,
-        can_tx_channel,
+        can_tx_queue,
+        can_cmd_channel,
+        rtr_table,
         wifi_rx_channel,
-        wifi_tx_channel,
+        host_channel,
+        peer_rx_channel,
+        peer_tx_channel,
+        peer_endpoint,
+        server_endpoint,
+        cannelloni_tx_channel,
+        cannelloni_endpoint,
         signal_conn_rx,
-        signal_conn_tx,
+        sessions,
+        socket_buffers,
+        secret,
         config,
     )
 }
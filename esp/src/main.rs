@@ -3,26 +3,45 @@
 mod macros;
 
 mod can;
+mod cannelloni;
 mod config;
+mod discovery;
 mod init;
+mod mdns;
+mod peer;
+mod stream;
 mod wifi;
 
+use core::future::pending;
+
 use corelib::ComItem;
 use embassy_executor::Spawner;
-use embassy_futures::select::{select, Either};
-use embassy_time::Instant;
-use embedded_can::Frame;
+use embassy_futures::select::{select4, Either4};
+use embassy_time::{with_deadline, Duration, Instant, Timer};
 
 use esp_alloc as _;
 use esp_backtrace as _;
+use log::warn;
 
 use corelib::*;
 use init::*;
 
-use crate::config::ConfigBuffer;
+use crate::wifi::{SessionMessage, Target, MAX_SESSIONS};
 
 esp_bootloader_esp_idf::esp_app_desc!();
 const FILTER_SIZE: usize = 10;
+const SNAPSHOT_SIZE: usize = 64;
+const ID_TABLE_SIZE: usize = 128;
+const CYCLIC_SIZE: usize = 8;
+const RULES_SIZE: usize = 16;
+const RULES_PENDING: usize = 16;
+const GATEWAY_SIZE: usize = 8;
+const TX_INTERLOCK_SIZE: usize = 10;
+const LOOP_GUARD_SIZE: usize = 32;
+/// Time a frame that crossed the tunnel is not allowed to cross back
+const LOOP_GUARD_WINDOW: Duration = Duration::from_millis(500);
+/// Longest wait of a reply with several items for a host that does not read
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
 
 #[esp_hal_embassy::main]
 async fn main(spawner: Spawner) -> ! {
@@ -32,102 +51,700 @@ async fn main(spawner: Spawner) -> ! {
         controller,
         twai,
         can_rx_channel,
-        can_tx_channel,
+        can_tx_queue,
+        can_cmd_channel,
+        rtr_table,
         wifi_rx_channel,
-        wifi_tx_channel,
+        host_channel,
+        peer_rx_channel,
+        peer_tx_channel,
+        peer_endpoint,
+        server_endpoint,
+        cannelloni_tx_channel,
+        cannelloni_endpoint,
         signal_conn_rx,
-        signal_conn_tx,
+        sessions,
+        socket_buffers,
+        secret,
         mut config,
     ) = init();
 
     config.load(wifi_rx_channel).await;
 
-    spawner.spawn(wifi::connection(controller)).ok();
-    spawner.spawn(wifi::net_task(runner)).ok();
+    // all tasks are needed, a task that does not fit into the arena of the executor stops the start
+    spawner.spawn(wifi::connection(controller)).unwrap();
+    spawner.spawn(wifi::net_task(runner)).unwrap();
+    let (listen_buffers, client_buffers) = socket_buffers.split_at_mut(wifi::LISTEN_SESSIONS);
+    for (session, buffers) in listen_buffers.iter_mut().enumerate() {
+        spawner
+            .spawn(wifi::comm(
+                session as u8,
+                stack,
+                wifi_rx_channel,
+                host_channel,
+                sessions,
+                secret,
+                buffers,
+            ))
+            .unwrap();
+    }
     spawner
-        .spawn(wifi::comm(
+        .spawn(wifi::client(
             stack,
             wifi_rx_channel,
-            wifi_tx_channel,
-            signal_conn_tx,
+            host_channel,
+            server_endpoint,
+            sessions,
+            secret,
+            &mut client_buffers[0],
+        ))
+        .unwrap();
+    spawner
+        .spawn(peer::comm(
+            stack,
+            peer_endpoint,
+            peer_rx_channel,
+            peer_tx_channel,
+            secret,
+        ))
+        .unwrap();
+    // frames of the cannelloni peer are transmitted like the frames of the peer bridge
+    spawner
+        .spawn(cannelloni::comm(
+            stack,
+            cannelloni_endpoint,
+            peer_rx_channel,
+            cannelloni_tx_channel,
         ))
-        .ok();
+        .unwrap();
+    spawner
+        .spawn(discovery::comm(stack, can::bitrate(CAN_BAUDRATE)))
+        .unwrap();
+    spawner
+        .spawn(mdns::comm(stack, can::bitrate(CAN_BAUDRATE)))
+        .unwrap();
     spawner
         .spawn(can::comm(
             twai,
             can_rx_channel,
-            can_tx_channel,
+            can_tx_queue,
+            can_cmd_channel,
+            rtr_table,
             signal_conn_rx,
         ))
-        .ok();
+        .unwrap();
 
-    let mut pfilters: PFilters<FILTER_SIZE> = PFilters::default();
-    let mut nfilters: NFilters<FILTER_SIZE> = NFilters::default();
+    // the persistent filters are the initial filters of every session
+    let mut global_filters: FilterSet<FILTER_SIZE> = FilterSet::default();
+    let mut session_filters: [Option<FilterSet<FILTER_SIZE>>; MAX_SESSIONS] =
+        [const { None }; MAX_SESSIONS];
+    let mut id_table: IdTable<ID_TABLE_SIZE> = IdTable::default();
+    let mut snapshot: Snapshot<SNAPSHOT_SIZE> = Snapshot::default();
+    let mut snapshot_period = 0_u32;
+    let mut next_snapshot = Instant::now();
+    let mut cyclic_jobs: CyclicJobs<CYCLIC_SIZE> = CyclicJobs::default();
+    let mut auto_recover = can::RECOVERY_BACKOFF;
+    let mut batch: Option<can::CanTxBatch> = None;
+    let mut rules: Rules<RULES_SIZE, RULES_PENDING> = Rules::default();
+    let mut gateway: Gateway<GATEWAY_SIZE> = Gateway::default();
+    let mut tx_interlock: TxInterlock<TX_INTERLOCK_SIZE> = TxInterlock::default();
+    let mut peer: Option<Endpoint> = None;
+    let mut server: Option<Endpoint> = None;
+    let mut cannelloni: Option<Endpoint> = None;
+    let mut peer_latency: Option<Latency> = None;
+    // the session allowed to change the bridge, all other sessions are read-only
+    let mut controller: Option<u8> = None;
+    // frames sent to the tunnels and frames received from the tunnels
+    let mut to_peer: LoopGuard<LOOP_GUARD_SIZE> = LoopGuard::new(LOOP_GUARD_WINDOW);
+    let mut from_peer: LoopGuard<LOOP_GUARD_SIZE> = LoopGuard::new(LOOP_GUARD_WINDOW);
+    // a host that stops reading loses the oldest items instead of stalling the bridge,
+    // replies with several items wait for room instead, see Reply
+    let publisher: HostPublisher = host_channel.publisher().unwrap();
 
     loop {
         let can_receive = async { can_rx_channel.receive().await };
         let wifi_receive = async { wifi_rx_channel.receive().await };
+        let peer_receive = async { peer_rx_channel.receive().await };
+        let next_snapshot_due = (snapshot_period != 0).then_some(next_snapshot);
+        let next_due = [next_snapshot_due, cyclic_jobs.next_due(), rules.next_due()]
+            .into_iter()
+            .flatten()
+            .min();
+        let timer = async move {
+            match next_due {
+                Some(instant) => Timer::at(instant).await,
+                None => pending::<()>().await,
+            }
+        };
 
-        // Wait for both and handle first event
-        match select(can_receive, wifi_receive).await {
-            Either::First(com_item) => {
+        // Wait for all and handle first event
+        match select4(can_receive, wifi_receive, peer_receive, timer).await {
+            Either4::First((target, com_item)) => {
                 if let ComItem::ReceivedFrame(frame) = &com_item {
-                    if !nfilters.check(frame.id()) && pfilters.check(frame.id(), Instant::now()) {
-                        wifi_tx_channel.send(com_item).await;
+                    // responses beyond the capacity of the rules are dropped
+                    let _ = rules.process(frame, Instant::now());
+                    while let Some(response) = rules.poll(Instant::now()) {
+                        let _ = transmit(&mut tx_interlock, can_tx_queue, response);
+                    }
+                    for translated in gateway.process(frame, Instant::now()) {
+                        // blocked or with a full queue the translated frame is dropped
+                        let _ = transmit(&mut tx_interlock, can_tx_queue, translated);
+                    }
+                    // ids beyond the capacity of the table are silently ignored
+                    let _ = id_table.update(frame, Instant::now());
+                    if global_filters.check(frame, Instant::now()) {
+                        // frames coming back from a tunnel are not tunnelled again
+                        let tunnel = peer.is_some() || cannelloni.is_some();
+                        if tunnel && !from_peer.check(frame, Instant::now()) {
+                            to_peer.record(frame, Instant::now());
+                            if peer.is_some() {
+                                let item = ComItem::FrameToSend(TxFrame::new(*frame));
+                                // frames are dropped while the peer is not connected
+                                let _ = peer_tx_channel.try_send(item);
+                            }
+                            if cannelloni.is_some() {
+                                // with a full queue the frame is dropped
+                                let _ =
+                                    cannelloni_tx_channel.try_send(ComItem::ReceivedFrame(*frame));
+                            }
+                        }
+                        if snapshot_period != 0 {
+                            // ids beyond the capacity of the cache are silently ignored
+                            let _ = snapshot.update(frame);
+                        }
                     }
+                    if snapshot_period == 0 {
+                        publish_frame(&publisher, &mut session_filters, frame);
+                    }
+                } else {
+                    // transmit results are not filtered
+                    publisher.publish_immediate((target, com_item));
+                }
+            }
+            Either4::Second(SessionMessage::Connected(session)) => {
+                let mut filters = global_filters.clone();
+                filters.clear_stats();
+                session_filters[session as usize] = Some(filters);
+                if controller.is_none() {
+                    controller = Some(session);
+                }
+            }
+            Either4::Second(SessionMessage::Disconnected(session)) => {
+                session_filters[session as usize] = None;
+                if controller == Some(session) {
+                    controller = None;
                 }
             }
-            Either::Second(com_item) => {
+            Either4::Second(SessionMessage::Item(origin, com_item)) => {
+                // the items of a session arrive before its disconnection
+                let Some(filters) = (match origin {
+                    Target::All => Some(&mut global_filters),
+                    Target::Session(session) => session_filters[session as usize].as_mut(),
+                }) else {
+                    continue;
+                };
+                // the items loaded from flash are always permitted
+                let permitted = match origin {
+                    Target::All => true,
+                    Target::Session(session) => {
+                        controller == Some(session) || !com_item.requires_control()
+                    }
+                };
+                if !permitted {
+                    let item = ComItem::Error(Error::NotPermitted);
+                    publisher.publish_immediate((origin, item));
+                    continue;
+                }
                 match com_item {
-                    ComItem::ClearFilters => {
-                        pfilters.clear();
-                        nfilters.clear();
+                    ComItem::AutoRecover(backoff) => {
+                        auto_recover = backoff;
+                        can_cmd_channel.send(com_item).await;
+                    }
+                    ComItem::BatchFrame(frame) => {
+                        let tx_frame = with_origin(*frame.tx_frame(), origin);
+                        let frame = BatchFrame::new(frame.delay(), tx_frame);
+                        let result = match batch.as_mut() {
+                            Some(batch) => tx_interlock
+                                .check(frame.tx_frame().frame())
+                                .and_then(|()| batch.push(frame)),
+                            None => Err(Error::NoBatch),
+                        };
+                        if let Err(error) = result {
+                            // an incomplete batch is never transmitted
+                            batch = None;
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::Abort => {
+                        batch = None;
+                        can_tx_queue.abort_batch();
+                    }
+                    ComItem::Begin => {
+                        // an open batch is kept, it is discarded by $abort
+                        if batch.is_some() {
+                            let item = ComItem::Error(Error::BatchOpen);
+                            publisher.publish_immediate((origin, item));
+                        } else {
+                            batch = Some(can::CanTxBatch::default());
+                        }
+                    }
+                    ComItem::BusRecover => can_cmd_channel.send(com_item).await,
+                    ComItem::Cannelloni(endpoint) => {
+                        cannelloni = endpoint;
+                        cannelloni_endpoint.sender().send(endpoint);
+                    }
+                    ComItem::ClearFilters => filters.clear(),
+                    ComItem::ClearRtr => rtr_table.clear(),
+                    ComItem::ClearStats => filters.clear_stats(),
+                    ComItem::Commit => {
+                        let result = match batch.take() {
+                            Some(batch) => can_tx_queue.commit(batch),
+                            None => Err(Error::NoBatch),
+                        };
+                        if let Err(error) = result {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
                     }
-                    ComItem::Echo | ComItem::Error(_) => wifi_tx_channel.send(com_item).await,
-                    ComItem::FrameToSend(_) => can_tx_channel.send(com_item).await,
-                    ComItem::NFilter(nfilter) => match nfilters.add(nfilter) {
+                    ComItem::Cyclic(job) => {
+                        let result = tx_interlock
+                            .check(job.frame())
+                            .and_then(|()| cyclic_jobs.start(job, Instant::now()));
+                        if let Err(error) = result {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::CyclicStop(slot) => {
+                        if let Err(error) = cyclic_jobs.stop(slot) {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::Echo | ComItem::Error(_) => {
+                        publisher.publish_immediate((origin, com_item))
+                    }
+                    ComItem::FrameToSend(tx_frame) => {
+                        let tx_frame = with_origin(tx_frame, origin);
+                        let result = tx_interlock
+                            .check(tx_frame.frame())
+                            .and_then(|()| can_tx_queue.push(tx_frame));
+                        if let Err(error) = result {
+                            let item = match tx_frame.tag() {
+                                Some(tag) => ComItem::TxErr(tag, error),
+                                None => ComItem::Error(error),
+                            };
+                            publisher.publish_immediate((origin, item));
+                        }
+                    }
+                    ComItem::Gateway(rule) => {
+                        if let Err(error) = gateway.set(rule) {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::GatewayDel(slot) => {
+                        if let Err(error) = gateway.remove(slot) {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    // control is only granted while nobody else has it
+                    ComItem::Lock => {
+                        if let Target::Session(session) = origin {
+                            match controller {
+                                Some(current) if current != session => {
+                                    let item = ComItem::Error(Error::NotPermitted);
+                                    publisher.publish_immediate((origin, item));
+                                }
+                                _ => controller = Some(session),
+                            }
+                        }
+                    }
+                    ComItem::NFilter(nfilter) => match filters.add_nfilter(nfilter) {
                         Ok(()) => (),
-                        Err(error) => wifi_tx_channel.send(ComItem::Error(error)).await,
+                        Err(error) => publisher.publish_immediate((origin, ComItem::Error(error))),
                     },
-                    ComItem::PFilter(pfilter) => match pfilters.add(pfilter) {
+                    ComItem::Peer(endpoint) => {
+                        peer = endpoint;
+                        peer_latency = None;
+                        to_peer.clear();
+                        from_peer.clear();
+                        peer_endpoint.sender().send(endpoint);
+                    }
+                    ComItem::PFilter(pfilter) => match filters.add_pfilter(pfilter) {
                         Ok(()) => (),
-                        Err(error) => wifi_tx_channel.send(ComItem::Error(error)).await,
+                        Err(error) => publisher.publish_immediate((origin, ComItem::Error(error))),
                     },
-                    ComItem::Save => save_config(
-                        &pfilters, 
-                        &nfilters,
-                        &mut config,
-                    ).unwrap(),
+                    // a peer bridge measures its round trip time
+                    ComItem::Ping(timestamp) => {
+                        publisher.publish_immediate((origin, ComItem::Pong(timestamp)));
+                    }
+                    ComItem::Rtr(response) => {
+                        let result = tx_interlock
+                            .check(response.frame())
+                            .and_then(|()| rtr_table.set(response));
+                        if let Err(error) = result {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::Rule(rule) => {
+                        if let Err(error) = rules.set(rule) {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::RuleDel(slot) => {
+                        if let Err(error) = rules.remove(slot) {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    // the filters of the session become the persistent filters
+                    ComItem::Save => {
+                        let mut saved = filters.clone();
+                        saved.clear_stats();
+                        global_filters = saved;
+                        let result = save_config(
+                            &global_filters,
+                            snapshot_period,
+                            &cyclic_jobs,
+                            auto_recover,
+                            &rtr_table.responders(),
+                            &rules,
+                            &gateway,
+                            &tx_interlock,
+                            peer,
+                            server,
+                            cannelloni,
+                            secret.lock(|secret| secret.get()),
+                            &mut config,
+                        );
+                        match result {
+                            Ok(()) => tx_interlock.saved(),
+                            Err(error) => {
+                                publisher.publish_immediate((origin, ComItem::Error(error)))
+                            }
+                        }
+                    }
+                    // sessions that have sent a command before stay authenticated
+                    ComItem::Secret(new_secret) => secret.lock(|secret| secret.set(new_secret)),
+                    ComItem::Server(endpoint) => {
+                        server = endpoint;
+                        server_endpoint.sender().send(endpoint);
+                    }
+                    ComItem::ShowCannelloni => {
+                        publisher.publish_immediate((origin, ComItem::Cannelloni(cannelloni)));
+                    }
+                    ComItem::ShowCyclic => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        for job in cyclic_jobs.iter() {
+                            reply.send(ComItem::Cyclic(*job)).await;
+                        }
+                    }
                     ComItem::ShowFilters => {
-                        for nfilter in nfilters.get_vec_ref() {
-                            wifi_tx_channel.send(ComItem::NFilter(*nfilter)).await;
+                        let mut reply = Reply::new(&publisher, origin);
+                        for nfilter in filters.nfilters().get_vec_ref() {
+                            reply.send(ComItem::NFilter(*nfilter)).await;
+                            reply.send(ComItem::FilterStats(nfilter.stats())).await;
+                        }
+                        for pfilter in filters.pfilters().get_vec_ref() {
+                            reply.send(ComItem::PFilter(pfilter.as_pre_pfilter())).await;
+                            reply.send(ComItem::FilterStats(pfilter.stats())).await;
+                        }
+                        if snapshot_period != 0 {
+                            reply.send(ComItem::Snapshot(snapshot_period)).await;
+                        }
+                        reply.send(ComItem::RxDropped(can::rx_dropped())).await;
+                    }
+                    ComItem::ShowGateway => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        for rule in gateway.iter() {
+                            reply.send(ComItem::Gateway(*rule)).await;
                         }
-                        for pfilter in pfilters.get_vec_ref() {
-                            wifi_tx_channel
-                                .send(ComItem::PFilter(pfilter.as_pre_pfilter()))
-                                .await;
+                    }
+                    ComItem::ShowIds => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        for entry in id_table.iter() {
+                            reply.send(ComItem::IdInfo(*entry)).await;
+                        }
+                    }
+                    ComItem::ShowLast(id) => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        let mut found = false;
+                        for entry in id_table.find(id) {
+                            found = true;
+                            reply.send(ComItem::LastFrame(*entry.frame())).await;
+                        }
+                        if !found {
+                            reply.send(ComItem::Error(Error::NotFound)).await;
+                        }
+                    }
+                    ComItem::ShowPeer => publisher.publish_immediate((origin, ComItem::Peer(peer))),
+                    ComItem::ShowPeerLatency => {
+                        let item = match peer_latency {
+                            Some(latency) => ComItem::PeerLatency(latency),
+                            None => ComItem::Error(Error::NotFound),
+                        };
+                        publisher.publish_immediate((origin, item));
+                    }
+                    ComItem::ShowRtr => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        for response in rtr_table.responders().iter() {
+                            reply.send(ComItem::Rtr(*response)).await;
+                        }
+                    }
+                    ComItem::ShowRules => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        for rule in rules.iter() {
+                            reply.send(ComItem::Rule(*rule)).await;
+                        }
+                    }
+                    ComItem::ShowServer => {
+                        publisher.publish_immediate((origin, ComItem::Server(server)))
+                    }
+                    ComItem::ShowTxInterlock => {
+                        let mut reply = Reply::new(&publisher, origin);
+                        for pattern in tx_interlock.allowed().get_vec_ref() {
+                            reply.send(ComItem::TxAllow(*pattern)).await;
+                            reply.send(ComItem::FilterStats(pattern.stats())).await;
                         }
+                        for pattern in tx_interlock.blocked().get_vec_ref() {
+                            reply.send(ComItem::TxBlock(*pattern)).await;
+                            reply.send(ComItem::FilterStats(pattern.stats())).await;
+                        }
+                        let editable = tx_interlock.is_editable();
+                        reply.send(ComItem::TxEdit(editable)).await;
+                    }
+                    ComItem::ShowTxQueue => {
+                        publisher.publish_immediate((
+                            origin,
+                            ComItem::TxQueue(can_tx_queue.depth() as u32),
+                        ));
                     }
+                    ComItem::Snapshot(period) => {
+                        snapshot_period = period;
+                        snapshot.clear();
+                        next_snapshot = Instant::now() + Duration::from_millis(period as u64);
+                    }
+                    // responses defined earlier must not bypass the changed interlock
+                    ComItem::TxAllow(pattern) => match tx_interlock.allow(pattern) {
+                        Ok(()) => {
+                            rtr_table.retain(|response| tx_interlock.passes(response.frame()))
+                        }
+                        Err(error) => publisher.publish_immediate((origin, ComItem::Error(error))),
+                    },
+                    ComItem::TxBlock(pattern) => match tx_interlock.block(pattern) {
+                        Ok(()) => {
+                            rtr_table.retain(|response| tx_interlock.passes(response.frame()))
+                        }
+                        Err(error) => publisher.publish_immediate((origin, ComItem::Error(error))),
+                    },
+                    ComItem::TxClear => {
+                        if let Err(error) = tx_interlock.clear() {
+                            publisher.publish_immediate((origin, ComItem::Error(error)));
+                        }
+                    }
+                    ComItem::TxEdit(editable) => match origin {
+                        Target::All => tx_interlock.load_editable(editable),
+                        Target::Session(_) => {
+                            if let Err(error) = tx_interlock.set_editable(editable) {
+                                publisher.publish_immediate((origin, ComItem::Error(error)));
+                            }
+                        }
+                    },
                     // these ComItems are not accepted from wifi
-                    ComItem::End | ComItem::Magic(_) | ComItem::ReceivedFrame(_) => (),
+                    ComItem::Auth(_)
+                    | ComItem::BridgeInfo(_)
+                    | ComItem::BusState(_)
+                    | ComItem::Discover
+                    | ComItem::End
+                    | ComItem::FilterStats(_)
+                    | ComItem::IdInfo(_)
+                    | ComItem::LastFrame(_)
+                    | ComItem::Magic(_)
+                    | ComItem::Nonce(_)
+                    | ComItem::PeerLatency(_)
+                    | ComItem::Pong(_)
+                    | ComItem::ReceivedFrame(_)
+                    | ComItem::RxDropped(_)
+                    | ComItem::Sequence(_)
+                    | ComItem::Stream(_)
+                    | ComItem::TxErr(_, _)
+                    | ComItem::TxOk(_, _)
+                    | ComItem::TxQueue(_) => (),
+                }
+            }
+            Either4::Third(com_item) => match com_item {
+                // the frames received by the peer are transmitted here
+                ComItem::ReceivedFrame(frame) => {
+                    // frames we sent to the peer are not tunnelled back
+                    if !to_peer.check(&frame, Instant::now()) {
+                        from_peer.record(&frame, Instant::now());
+                        // blocked or with a full queue the frame is dropped
+                        let _ = transmit(&mut tx_interlock, can_tx_queue, frame);
+                    }
+                }
+                ComItem::Pong(timestamp) => {
+                    let rtt = (Instant::now().as_millis() as u32).wrapping_sub(timestamp);
+                    match peer_latency.as_mut() {
+                        Some(latency) => latency.update(rtt),
+                        None => peer_latency = Some(Latency::new(rtt)),
+                    }
+                }
+                // the errors of the peer are logged by the peer task
+                _ => (),
+            },
+            Either4::Fourth(()) => {
+                let now = Instant::now();
+                while let Some(frame) = cyclic_jobs.poll(now) {
+                    // blocked or with a full queue the frame is skipped for this period
+                    let _ = transmit(&mut tx_interlock, can_tx_queue, frame);
+                }
+                while let Some(frame) = rules.poll(now) {
+                    // blocked or with a full queue the response is dropped
+                    let _ = transmit(&mut tx_interlock, can_tx_queue, frame);
+                }
+                if snapshot_period != 0 && next_snapshot <= now {
+                    // the filters of each session select its frames of the snapshot
+                    for frame in snapshot.take_changed() {
+                        publish_frame(&publisher, &mut session_filters, &frame);
+                    }
+                    let period = Duration::from_millis(snapshot_period as u64);
+                    next_snapshot += period;
+                    if next_snapshot < Instant::now() {
+                        next_snapshot = Instant::now() + period;
+                    }
                 }
             }
         };
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn save_config(
-    pfilters: &PFilters<FILTER_SIZE>, 
-    nfilters: &NFilters<FILTER_SIZE>,
+    filters: &FilterSet<FILTER_SIZE>,
+    snapshot_period: u32,
+    cyclic_jobs: &CyclicJobs<CYCLIC_SIZE>,
+    auto_recover: Backoff,
+    rtr_responders: &can::CanRtrResponders,
+    rules: &Rules<RULES_SIZE, RULES_PENDING>,
+    gateway: &Gateway<GATEWAY_SIZE>,
+    tx_interlock: &TxInterlock<TX_INTERLOCK_SIZE>,
+    peer: Option<Endpoint>,
+    server: Option<Endpoint>,
+    cannelloni: Option<Endpoint>,
+    secret: Option<Secret>,
     config: &mut config::Config,
 ) -> Result<(), Error> {
-    let mut buf = ConfigBuffer::default();
-    for pfilter in pfilters.get_vec_ref() {
+    let mut buf = config.buffer();
+    for pfilter in filters.pfilters().get_vec_ref() {
         buf.add_item(&ComItem::PFilter(pfilter.as_pre_pfilter()))?;
     }
-    for nfilter in nfilters.get_vec_ref() {
+    for nfilter in filters.nfilters().get_vec_ref() {
         buf.add_item(&ComItem::NFilter(*nfilter))?;
     }
-    buf.finish(config)?;
+    if snapshot_period != 0 {
+        buf.add_item(&ComItem::Snapshot(snapshot_period))?;
+    }
+    // the interlock is restored before the frames it has to check
+    for pattern in tx_interlock.allowed().get_vec_ref() {
+        buf.add_item(&ComItem::TxAllow(*pattern))?;
+    }
+    for pattern in tx_interlock.blocked().get_vec_ref() {
+        buf.add_item(&ComItem::TxBlock(*pattern))?;
+    }
+    if !tx_interlock.is_editable() {
+        buf.add_item(&ComItem::TxEdit(false))?;
+    }
+    // jobs that are not persistent, e.g. a temporary keep-alive, run until the next restart
+    for job in cyclic_jobs.iter().filter(|job| job.is_persistent()) {
+        buf.add_item(&ComItem::Cyclic(*job))?;
+    }
+    if auto_recover != can::RECOVERY_BACKOFF {
+        buf.add_item(&ComItem::AutoRecover(auto_recover))?;
+    }
+    for response in rtr_responders.iter() {
+        buf.add_item(&ComItem::Rtr(*response))?;
+    }
+    for rule in rules.iter() {
+        buf.add_item(&ComItem::Rule(*rule))?;
+    }
+    for rule in gateway.iter() {
+        buf.add_item(&ComItem::Gateway(*rule))?;
+    }
+    if peer.is_some() {
+        buf.add_item(&ComItem::Peer(peer))?;
+    }
+    if server.is_some() {
+        buf.add_item(&ComItem::Server(server))?;
+    }
+    if cannelloni.is_some() {
+        buf.add_item(&ComItem::Cannelloni(cannelloni))?;
+    }
+    if secret.is_some() {
+        buf.add_item(&ComItem::Secret(secret))?;
+    }
+    buf.finish()?;
     Ok(())
+}
+
+/// Sends a received frame to every session whose filters it passes
+fn publish_frame(
+    publisher: &HostPublisher,
+    session_filters: &mut [Option<FilterSet<FILTER_SIZE>>; MAX_SESSIONS],
+    frame: &CanFrame,
+) {
+    for (session, filters) in session_filters.iter_mut().enumerate() {
+        let Some(filters) = filters else { continue };
+        if filters.check(frame, Instant::now()) {
+            let target = Target::Session(session as u8);
+            publisher.publish_immediate((target, ComItem::ReceivedFrame(*frame)));
+        }
+    }
+}
+
+/// Queues a frame generated by the bridge itself if the transmit interlock lets it pass
+fn transmit(
+    tx_interlock: &mut TxInterlock<TX_INTERLOCK_SIZE>,
+    can_tx_queue: &can::CanTxQueue,
+    frame: CanFrame,
+) -> Result<(), Error> {
+    tx_interlock.check(&frame)?;
+    can_tx_queue.push(TxFrame::new(frame))
+}
+
+/// Marks a frame queued by a session, the session gets its transmission results
+fn with_origin(tx_frame: TxFrame, origin: Target) -> TxFrame {
+    match origin {
+        Target::All => tx_frame,
+        Target::Session(session) => tx_frame.with_origin(session),
+    }
+}
+
+/// A reply of several items to one session
+///
+/// The items wait for room in the host channel instead of evicting the items queued for the
+/// other sessions. A host that stops reading cuts the reply short after `REPLY_TIMEOUT`
+/// instead of stalling the bridge.
+struct Reply<'a> {
+    publisher: &'a HostPublisher,
+    target: Target,
+    deadline: Instant,
+    cut: bool,
+}
+
+impl<'a> Reply<'a> {
+    fn new(publisher: &'a HostPublisher, target: Target) -> Self {
+        Self {
+            publisher,
+            target,
+            deadline: Instant::now() + REPLY_TIMEOUT,
+            cut: false,
+        }
+    }
+
+    async fn send(&mut self, item: ComItem) {
+        if self.cut {
+            return;
+        }
+        let publish = self.publisher.publish((self.target, item));
+        if with_deadline(self.deadline, publish).await.is_err() {
+            warn!("reply cut short, the host does not read");
+            self.cut = true;
+        }
+    }
 }
\ No newline at end of file
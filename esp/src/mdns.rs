@@ -0,0 +1,104 @@
+use embassy_futures::select::{select, Either};
+use embassy_net::{
+    udp::{PacketMetadata, UdpSocket},
+    Stack,
+};
+use embassy_time::{Duration, Instant, Timer};
+
+use esp_alloc as _;
+use esp_backtrace as _;
+
+use log::{info, warn};
+
+use crate::{
+    discovery,
+    wifi::{ip_endpoint, SESSION_PORT},
+};
+use corelib::{BridgeInfo, Endpoint, MdnsResponder, MDNS_ADDR, MDNS_PACKET_LEN, MDNS_PORT};
+
+/// Announcements sent when the bridge gets an address, one second apart as required
+const ANNOUNCEMENTS: usize = 2;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// Time between the checks of the address, the address may change on a DHCP renewal
+const ADDRESS_POLL: Duration = Duration::from_secs(1);
+
+/// Makes the bridge findable by mDNS and DNS-SD
+///
+/// Queries received on the multicast group are answered on the group, legacy unicast
+/// queries from ports other than 5353 are not answered.
+#[embassy_executor::task]
+pub async fn comm(stack: Stack<'static>, bitrate: u32) {
+    let rx_meta = mk_static!([PacketMetadata; 4], [PacketMetadata::EMPTY; 4]);
+    let rx_buffer = mk_static!([u8; 2048], [0; 2048]);
+    let tx_meta = mk_static!([PacketMetadata; 2], [PacketMetadata::EMPTY; 2]);
+    let tx_buffer = mk_static!([u8; 1024], [0; 1024]);
+    let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+    if let Err(e) = socket.bind(MDNS_PORT) {
+        warn!("mdns bind error: {e:?}");
+        return;
+    }
+    let group = ip_endpoint(&Endpoint::new(MDNS_ADDR, MDNS_PORT));
+    if let Err(e) = stack.join_multicast_group(group.addr) {
+        warn!("mdns join error: {e:?}");
+        return;
+    }
+    let name = discovery::name();
+    let version = env!("CARGO_PKG_VERSION");
+    let mut address = None;
+    let mut announcements = 0;
+    let mut next_announcement = Instant::now();
+    let mut query = [0; MDNS_PACKET_LEN];
+
+    loop {
+        let config_address = stack.config_v4().map(|c| c.address.address().octets());
+        if config_address != address {
+            address = config_address;
+            announcements = ANNOUNCEMENTS;
+            next_announcement = Instant::now();
+        }
+        let Some(addr) = address else {
+            Timer::after(ADDRESS_POLL).await;
+            continue;
+        };
+        let endpoint = Endpoint::new(addr, SESSION_PORT);
+        // the name and the version are valid texts
+        let Ok(responder) =
+            BridgeInfo::new(&name, endpoint, version, bitrate).and_then(MdnsResponder::new)
+        else {
+            return;
+        };
+
+        if announcements > 0 && Instant::now() >= next_announcement {
+            if announcements == ANNOUNCEMENTS {
+                info!("Announcing {} by mDNS", responder.host());
+            }
+            if let Ok(packet) = responder.announce::<MDNS_PACKET_LEN>() {
+                if let Err(e) = socket.send_to(packet.as_slice(), group).await {
+                    warn!("mdns send error: {e:?}");
+                }
+            }
+            announcements -= 1;
+            next_announcement = Instant::now() + ANNOUNCE_INTERVAL;
+        }
+        let timer = match announcements {
+            0 => Timer::after(ADDRESS_POLL),
+            _ => Timer::at(next_announcement),
+        };
+
+        match select(socket.recv_from(&mut query), timer).await {
+            Either::First(Ok((n, meta))) => {
+                if meta.endpoint.port != MDNS_PORT {
+                    continue;
+                }
+                // malformed queries and queries about other hosts are ignored
+                if let Ok(Some(packet)) = responder.answer::<MDNS_PACKET_LEN>(&query[..n]) {
+                    if let Err(e) = socket.send_to(packet.as_slice(), group).await {
+                        warn!("mdns send error: {e:?}");
+                    }
+                }
+            }
+            Either::First(Err(e)) => warn!("mdns receive error: {e:?}"),
+            Either::Second(()) => (),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_net::{tcp::TcpSocket, Stack};
+use embassy_time::{Duration, Instant, Timer};
+
+use esp_alloc as _;
+use esp_backtrace as _;
+
+use embedded_io_async::Write;
+use log::{info, warn};
+
+use crate::{wifi::ip_endpoint, ComChannel, EndpointWatch, SecretCell};
+use corelib::{Backoff, ComItem, DeSer, Error, RxBuffer, Serialize, DATAGRAM_LEN};
+
+/// Delays between the attempts to connect to the peer
+const PEER_BACKOFF: Backoff = Backoff::new(500, 30_000);
+/// Period of the round trip measurement
+const PING_PERIOD: Duration = Duration::from_secs(1);
+
+/// Connects to the peer bridge and relays the items between the peer and the main loop
+///
+/// Items for the peer that are queued while no peer is connected are discarded.
+#[embassy_executor::task]
+pub async fn comm(
+    stack: Stack<'static>,
+    peer_endpoint: &'static EndpointWatch,
+    peer_rx_channel: &'static ComChannel,
+    peer_tx_channel: &'static ComChannel,
+    secret: &'static SecretCell,
+) {
+    let rx_buffer = mk_static!([u8; 2048], [0; 2048]);
+    let tx_buffer = mk_static!([u8; 2048], [0; 2048]);
+    let mut endpoint_rx = peer_endpoint.receiver().unwrap();
+    let mut backoff = PEER_BACKOFF;
+
+    loop {
+        let Some(endpoint) = endpoint_rx.get().await else {
+            endpoint_rx.changed().await;
+            continue;
+        };
+        while stack.config_v4().is_none() {
+            Timer::after(Duration::from_millis(500)).await;
+        }
+
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer[..], &mut tx_buffer[..]);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        let remote = ip_endpoint(&endpoint);
+        info!("Connecting to peer {:?}...", remote);
+        match select(socket.connect(remote), endpoint_rx.changed()).await {
+            Either::First(Ok(())) => {
+                info!("Connected to peer");
+                backoff.reset();
+            }
+            Either::First(Err(e)) => {
+                warn!("peer connect error: {e:?}");
+                socket.abort();
+                let delay = backoff.next_delay().unwrap_or_default();
+                let retry = Timer::after(Duration::from_millis(delay as u64));
+                select(retry, endpoint_rx.changed()).await;
+                continue;
+            }
+            // connect to the new endpoint
+            Either::Second(_) => continue,
+        }
+
+        // frames queued while no peer was connected are outdated
+        while peer_tx_channel.try_receive().is_ok() {}
+        let relay = relay(&mut socket, peer_rx_channel, peer_tx_channel, secret);
+        match select(relay, endpoint_rx.changed()).await {
+            Either::First(()) => warn!("Peer connection closed"),
+            Either::Second(_) => info!("Peer changed"),
+        }
+        socket.abort();
+        let _ = socket.flush().await;
+    }
+}
+
+/// Relays items until the connection fails
+async fn relay(
+    socket: &mut TcpSocket<'_>,
+    peer_rx_channel: &'static ComChannel,
+    peer_tx_channel: &'static ComChannel,
+    secret: &'static SecretCell,
+) {
+    // the frames of the tunnel are transmitted by the other bridge, which needs its control,
+    // a host controlling the other bridge keeps it
+    let ser = ComItem::Lock.serialize();
+    if socket.write_all(ser.as_slice()).await.is_err() {
+        return;
+    }
+    let mut rxbuf = RxBuffer::<2048>::default();
+    let mut next_ping = Instant::now();
+    // while the peer rejects the frames, the lock is retried with every ping
+    let mut rejected = false;
+    loop {
+        let item = async { peer_tx_channel.receive().await };
+        let ping = Timer::at(next_ping);
+        let read = async { socket.read(rxbuf.en_mut_block()).await };
+
+        let item = match select3(item, ping, read).await {
+            Either3::First(item) => item,
+            Either3::Second(()) => {
+                next_ping = Instant::now() + PING_PERIOD;
+                if rejected {
+                    rejected = false;
+                    let ser = ComItem::Lock.serialize();
+                    if socket.write_all(ser.as_slice()).await.is_err() {
+                        return;
+                    }
+                }
+                ComItem::Ping(Instant::now().as_millis() as u32)
+            }
+            Either3::Third(Ok(0)) | Either3::Third(Err(_)) => return,
+            Either3::Third(Ok(n)) => {
+                rxbuf.set_head(n);
+                loop {
+                    let mut de_ser = DeSer::<DATAGRAM_LEN>::default();
+                    match rxbuf.read(&mut de_ser) {
+                        Ok(()) => (),
+                        Err(Error::BufIsEmpty) => break,
+                        Err(error) => {
+                            warn!("peer receive error: {error:?}");
+                            break;
+                        }
+                    }
+                    match ComItem::deserialize(&mut de_ser) {
+                        // the peer measures its round trip time as well
+                        Ok(ComItem::Ping(timestamp)) => {
+                            let ser = ComItem::Pong(timestamp).serialize();
+                            if socket.write_all(ser.as_slice()).await.is_err() {
+                                return;
+                            }
+                        }
+                        // both bridges share the secret, the lock failed before the answer
+                        Ok(ComItem::Nonce(nonce)) => {
+                            let Some(secret) = secret.lock(|secret| secret.get()) else {
+                                warn!("peer requires a secret");
+                                return;
+                            };
+                            let auth = ComItem::Auth(secret.sign(nonce.as_bytes()));
+                            for item in [auth, ComItem::Lock] {
+                                let ser = item.serialize();
+                                if socket.write_all(ser.as_slice()).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        Ok(ComItem::Error(Error::NotPermitted)) => {
+                            if !rejected {
+                                warn!("peer is controlled by another host, frames are rejected");
+                                rejected = true;
+                            }
+                        }
+                        Ok(ComItem::Error(error)) => warn!("peer error: {error:?}"),
+                        Ok(item) => peer_rx_channel.send(item).await,
+                        Err(error) => warn!("peer sent invalid item: {error:?}"),
+                    }
+                }
+                continue;
+            }
+        };
+        let ser = item.serialize();
+        if socket.write_all(ser.as_slice()).await.is_err() {
+            return;
+        }
+    }
+}
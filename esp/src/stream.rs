@@ -0,0 +1,107 @@
+use core::{future::poll_fn, task::Poll};
+
+use embassy_net::{
+    udp::{PacketMetadata, UdpSocket},
+    IpEndpoint, Stack,
+};
+use embassy_time::{Duration, Instant};
+
+use esp_alloc as _;
+use esp_backtrace as _;
+
+use log::warn;
+
+use corelib::{CanFrame, Serialize, StreamEncoder, STREAM_PACKET_LEN};
+
+/// Time the first frame of a packet waits for further frames
+const STREAM_DELAY: Duration = Duration::from_millis(10);
+/// Packets waiting to be sent, the host never sends anything
+const STREAM_PACKETS: usize = 4;
+
+#[derive(Clone, Copy)]
+pub struct UdpBuffers {
+    rx_meta: [PacketMetadata; 1],
+    rx: [u8; 64],
+    tx_meta: [PacketMetadata; STREAM_PACKETS],
+    tx: [u8; STREAM_PACKETS * STREAM_PACKET_LEN],
+}
+
+impl UdpBuffers {
+    pub const fn new() -> Self {
+        Self {
+            rx_meta: [PacketMetadata::EMPTY; 1],
+            rx: [0; 64],
+            tx_meta: [PacketMetadata::EMPTY; STREAM_PACKETS],
+            tx: [0; STREAM_PACKETS * STREAM_PACKET_LEN],
+        }
+    }
+
+    /// Opens the socket of a session on a free local port
+    pub fn socket(&mut self, stack: Stack<'static>) -> UdpSocket<'_> {
+        let mut socket = UdpSocket::new(
+            stack,
+            &mut self.rx_meta,
+            &mut self.rx,
+            &mut self.tx_meta,
+            &mut self.tx,
+        );
+        if let Err(e) = socket.bind(0) {
+            warn!("udp bind error: {e:?}");
+        }
+        socket
+    }
+}
+
+/// Received frames of a session sent to a UDP port of the host
+///
+/// Frames are collected for a short time and sent together. A packet that cannot be sent
+/// is dropped, the host detects the gap by the sequence number.
+pub struct Stream {
+    endpoint: IpEndpoint,
+    encoder: StreamEncoder<STREAM_PACKET_LEN>,
+    deadline: Instant,
+}
+
+impl Stream {
+    pub fn new(endpoint: IpEndpoint) -> Self {
+        Self {
+            endpoint,
+            encoder: StreamEncoder::default(),
+            deadline: Instant::now(),
+        }
+    }
+
+    pub async fn push(&mut self, socket: &mut UdpSocket<'_>, frame: &CanFrame) {
+        let mut first = self.encoder.is_empty();
+        if self.encoder.push(frame).is_err() {
+            self.flush(socket).await;
+            // an empty packet has space for a frame
+            let _ = self.encoder.push(frame);
+            first = true;
+        }
+        if first {
+            self.deadline = Instant::now() + STREAM_DELAY;
+        }
+    }
+
+    /// Sends the collected frames, the packet is dropped if the socket buffer is full
+    pub async fn flush(&mut self, socket: &mut UdpSocket<'_>) {
+        let Some(packet) = self.encoder.take() else {
+            return;
+        };
+        // the send is polled only once, waiting for the buffer would stall the session
+        let sent =
+            poll_fn(|cx| Poll::Ready(socket.poll_send_to(packet.as_slice(), self.endpoint, cx)))
+                .await;
+        match sent {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(e)) => warn!("udp send error: {e:?}"),
+            Poll::Pending => warn!("udp buffer full, packet dropped"),
+        }
+    }
+
+    /// Time to send the pending packet
+    pub fn deadline(&self) -> Option<Instant> {
+        (!self.encoder.is_empty()).then_some(self.deadline)
+    }
+}
@@ -1,7 +1,19 @@
-use embassy_futures::select::{select, Either};
-use embassy_net::{tcp::TcpSocket, Runner, Stack};
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, watch::Sender};
+use core::{
+    cell::Cell,
+    future::{pending, Future},
+};
+
+use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_net::{
+    tcp::TcpSocket, udp::UdpSocket, IpAddress, IpEndpoint, Ipv4Address, Runner, Stack,
+};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    pubsub::WaitResult,
+    watch::Sender,
+};
 use embassy_time::{Duration, Timer};
+use esp_hal::rng::Rng;
 
 use esp_alloc as _;
 use esp_backtrace as _;
@@ -12,25 +24,110 @@ use esp_radio::wifi::{
 use embedded_io_async::Write;
 use log::{error, info, warn};
 
-use crate::ComChannel;
-use corelib::{ComItem, DeSer, Error, RxBuffer, Serialize};
+use crate::{
+    stream::{Stream, UdpBuffers},
+    EndpointWatch, HostChannel, HostSubscriber, SecretCell, SessionChannel,
+};
+use corelib::{
+    Backoff, ComItem, DeSer, Endpoint, Error, Handshake, Nonce, RxBuffer, Serialize, DATAGRAM_LEN,
+    NONCE_LEN,
+};
 
 const SSID: &str = env!("SSID");
 const PASSWORD: &str = env!("PASSWORD");
+/// Delays between the attempts to connect to the configured server
+const SERVER_BACKOFF: Backoff = Backoff::new(500, 30_000);
+const SOCKET_BUFFER_SIZE: usize = 4096;
+/// TCP port of the sessions
+pub const SESSION_PORT: u16 = 1234;
+/// Sessions waiting for hosts on port 1234
+pub const LISTEN_SESSIONS: usize = 3;
+/// The listening sessions and the session connecting to the configured server
+pub const MAX_SESSIONS: usize = LISTEN_SESSIONS + 1;
+/// Id of the session connecting to the configured server
+pub const CLIENT_SESSION: u8 = LISTEN_SESSIONS as u8;
 
-#[embassy_executor::task]
-pub async fn comm(
-    stack: Stack<'static>,
-    wifi_rx_channel: &'static ComChannel,
-    wifi_tx_channel: &'static ComChannel,
-    set_connection: Sender<'static, CriticalSectionRawMutex, bool, 1>,
-) {
-    let rx_buffer = mk_static!([u8; 4096], [0; 4096]);
-    let tx_buffer = mk_static!([u8; 4096], [0; 4096]);
-    let mut rxbuf = RxBuffer::<2048>::default();
-    //let mut txbuf = [0_u8; 4096];
-    set_connection.send(false);
+/// Receivers of the items for the hosts
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Target {
+    All,
+    Session(u8),
+}
 
+/// Messages of the sessions for the main loop
+pub enum SessionMessage {
+    Connected(u8),
+    Disconnected(u8),
+    Item(Target, ComItem),
+}
+
+impl Target {
+    fn includes(&self, session: u8) -> bool {
+        match self {
+            Target::All => true,
+            Target::Session(id) => *id == session,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SocketBuffers {
+    rx: [u8; SOCKET_BUFFER_SIZE],
+    tx: [u8; SOCKET_BUFFER_SIZE],
+    udp: UdpBuffers,
+}
+
+impl SocketBuffers {
+    pub const fn new() -> Self {
+        Self {
+            rx: [0; SOCKET_BUFFER_SIZE],
+            tx: [0; SOCKET_BUFFER_SIZE],
+            udp: UdpBuffers::new(),
+        }
+    }
+}
+
+/// Number of connected sessions, the connection watch shows if any session is connected
+pub struct Sessions {
+    count: Mutex<CriticalSectionRawMutex, Cell<u8>>,
+    connection: Sender<'static, CriticalSectionRawMutex, bool, 1>,
+}
+
+impl Sessions {
+    pub fn new(connection: Sender<'static, CriticalSectionRawMutex, bool, 1>) -> Self {
+        connection.send(false);
+        Self {
+            count: Mutex::new(Cell::new(0)),
+            connection,
+        }
+    }
+
+    fn connect(&self) {
+        let count = self.count.lock(|count| {
+            count.set(count.get() + 1);
+            count.get()
+        });
+        self.connection.send(count > 0);
+    }
+
+    fn disconnect(&self) {
+        let count = self.count.lock(|count| {
+            count.set(count.get().saturating_sub(1));
+            count.get()
+        });
+        self.connection.send(count > 0);
+    }
+}
+
+pub fn ip_endpoint(endpoint: &Endpoint) -> IpEndpoint {
+    let [a, b, c, d] = endpoint.addr();
+    IpEndpoint::new(
+        IpAddress::Ipv4(Ipv4Address::new(a, b, c, d)),
+        endpoint.port(),
+    )
+}
+
+async fn wait_for_ip(stack: Stack<'static>) {
     loop {
         if stack.is_link_up() {
             break;
@@ -46,57 +143,277 @@ pub async fn comm(
         }
         Timer::after(Duration::from_millis(500)).await;
     }
+}
 
-    let mut socket = TcpSocket::new(stack, rx_buffer, tx_buffer);
+/// Waits for a host on port 1234, several sessions can be connected at the same time
+#[embassy_executor::task(pool_size = LISTEN_SESSIONS)]
+pub async fn comm(
+    session: u8,
+    stack: Stack<'static>,
+    wifi_rx_channel: &'static SessionChannel,
+    host_channel: &'static HostChannel,
+    sessions: &'static Sessions,
+    secret: &'static SecretCell,
+    buffers: &'static mut SocketBuffers,
+) {
+    wait_for_ip(stack).await;
 
     loop {
-        info!("Listening on TCP:1234...");
-        if let Err(e) = socket.accept(1234).await {
+        let mut socket = TcpSocket::new(stack, &mut buffers.rx[..], &mut buffers.tx[..]);
+        info!("Session {session}: listening on TCP:{SESSION_PORT}...");
+        if let Err(e) = socket.accept(SESSION_PORT).await {
             warn!("accept error: {e:?}");
             continue;
         }
-        set_connection.send(true);
-        info!("Received connection from {:?}", socket.remote_endpoint());
+        run_session(
+            session,
+            stack,
+            &mut socket,
+            &mut buffers.udp,
+            wifi_rx_channel,
+            host_channel,
+            sessions,
+            secret,
+            pending(),
+        )
+        .await;
+    }
+}
+
+/// Connects to the configured server instead of waiting for a host
+#[embassy_executor::task]
+pub async fn client(
+    stack: Stack<'static>,
+    wifi_rx_channel: &'static SessionChannel,
+    host_channel: &'static HostChannel,
+    server_endpoint: &'static EndpointWatch,
+    sessions: &'static Sessions,
+    secret: &'static SecretCell,
+    buffers: &'static mut SocketBuffers,
+) {
+    let mut server_rx = server_endpoint.receiver().unwrap();
+    let mut backoff = SERVER_BACKOFF;
+    wait_for_ip(stack).await;
 
-        loop {
-            if !socket.may_recv() {
-                set_connection.send(false);
-                socket.abort();
-                warn!("Connection closed");
-                break;
+    loop {
+        let Some(endpoint) = server_rx.get().await else {
+            server_rx.changed().await;
+            continue;
+        };
+        let mut socket = TcpSocket::new(stack, &mut buffers.rx[..], &mut buffers.tx[..]);
+        info!("Connecting to {:?}...", ip_endpoint(&endpoint));
+        match select(socket.connect(ip_endpoint(&endpoint)), server_rx.changed()).await {
+            Either::First(Ok(())) => backoff.reset(),
+            Either::First(Err(e)) => {
+                warn!("connect error: {e:?}");
+                let delay = backoff.next_delay().unwrap_or_default();
+                let retry = Timer::after(Duration::from_millis(delay as u64));
+                select(retry, server_rx.changed()).await;
+                continue;
             }
+            Either::Second(_) => continue,
+        }
+        // a changed server closes the connection
+        let server_changed = async {
+            server_rx.changed().await;
+        };
+        run_session(
+            CLIENT_SESSION,
+            stack,
+            &mut socket,
+            &mut buffers.udp,
+            wifi_rx_channel,
+            host_channel,
+            sessions,
+            secret,
+            server_changed,
+        )
+        .await;
+    }
+}
 
-            match socket_write_read(&mut socket, wifi_rx_channel, wifi_tx_channel, &mut rxbuf).await
-            {
-                Ok(()) => (),
-                Err(error) => wifi_tx_channel.send(ComItem::Error(error)).await,
+/// A host connected to the bridge
+///
+/// With a shared secret the host has to answer the challenge first, until then it gets no items
+/// and the main loop does not know about it.
+struct Session {
+    id: u8,
+    handshake: Handshake,
+    connected: bool,
+    remote: Option<IpAddress>,
+    stream: Option<Stream>,
+    wifi_rx_channel: &'static SessionChannel,
+    sessions: &'static Sessions,
+    secret: &'static SecretCell,
+}
+
+impl Session {
+    fn is_authenticated(&self) -> bool {
+        let secret = self.secret.lock(|secret| secret.get());
+        self.handshake.is_authenticated(secret.as_ref())
+    }
+
+    /// Reports the session to the main loop as soon as it is authenticated
+    async fn announce(&mut self) {
+        if !self.connected && self.is_authenticated() {
+            self.connected = true;
+            self.sessions.connect();
+            self.wifi_rx_channel
+                .send(SessionMessage::Connected(self.id))
+                .await;
+        }
+    }
+
+    async fn receive(&mut self, item: ComItem) -> Result<(), Error> {
+        let secret = self.secret.lock(|secret| secret.get());
+        match self.handshake.check(secret.as_ref(), item)? {
+            // the stream is handled by the session, the main loop does not know about it
+            Some(ComItem::Stream(port)) => {
+                self.stream = match (port, self.remote) {
+                    (0, _) | (_, None) => None,
+                    (port, Some(addr)) => Some(Stream::new(IpEndpoint::new(addr, port))),
+                };
             }
+            Some(item) => {
+                self.wifi_rx_channel
+                    .send(SessionMessage::Item(Target::Session(self.id), item))
+                    .await;
+            }
+            None => (),
+        }
+        self.announce().await;
+        Ok(())
+    }
+
+    async fn close(&mut self) {
+        if self.connected {
+            self.connected = false;
+            self.sessions.disconnect();
+            self.wifi_rx_channel
+                .send(SessionMessage::Disconnected(self.id))
+                .await;
         }
     }
 }
 
+fn new_nonce() -> Nonce {
+    let rng = Rng::new();
+    let mut bytes = [0; NONCE_LEN];
+    for chunk in bytes.chunks_mut(4) {
+        chunk.copy_from_slice(&rng.random().to_le_bytes());
+    }
+    Nonce::new(bytes)
+}
+
+/// Exchanges items with a connected host until the connection is closed or `close` finishes
+#[allow(clippy::too_many_arguments)]
+async fn run_session(
+    id: u8,
+    stack: Stack<'static>,
+    socket: &mut TcpSocket<'_>,
+    udp_buffers: &mut UdpBuffers,
+    wifi_rx_channel: &'static SessionChannel,
+    host_channel: &'static HostChannel,
+    sessions: &'static Sessions,
+    secret: &'static SecretCell,
+    close: impl Future<Output = ()>,
+) {
+    info!(
+        "Session {id}: connection with {:?}",
+        socket.remote_endpoint()
+    );
+    let Ok(mut subscriber) = host_channel.subscriber() else {
+        error!("Session {id}: no subscriber left");
+        socket.abort();
+        let _ = socket.flush().await;
+        return;
+    };
+    let mut session = Session {
+        id,
+        handshake: Handshake::new(new_nonce()),
+        connected: false,
+        remote: socket.remote_endpoint().map(|endpoint| endpoint.addr),
+        stream: None,
+        wifi_rx_channel,
+        sessions,
+        secret,
+    };
+    let mut rxbuf = RxBuffer::<2048>::default();
+    let mut udp = udp_buffers.socket(stack);
+
+    let exchange = async {
+        if !session.is_authenticated() {
+            let ser = session.handshake.challenge().serialize();
+            if socket.write_all(ser.as_slice()).await.is_err() {
+                return;
+            }
+        }
+        session.announce().await;
+        while socket.may_recv() {
+            let result =
+                socket_write_read(&mut session, socket, &mut udp, &mut subscriber, &mut rxbuf)
+                    .await;
+            if let Err(error) = result {
+                let ser = ComItem::Error(error).serialize();
+                if socket.write_all(ser.as_slice()).await.is_err() {
+                    error!("Socket write error");
+                }
+                if error == Error::AuthFailed {
+                    warn!("Session {id}: authentication failed");
+                    break;
+                }
+            }
+        }
+    };
+    select(exchange, close).await;
+
+    session.close().await;
+    udp.close();
+    socket.abort();
+    let _ = socket.flush().await;
+    warn!("Session {id}: connection closed");
+}
+
 async fn socket_write_read(
-    socket: &mut TcpSocket<'static>,
-    wifi_rx_channel: &'static ComChannel,
-    wifi_tx_channel: &'static ComChannel,
+    session: &mut Session,
+    socket: &mut TcpSocket<'_>,
+    udp: &mut UdpSocket<'_>,
+    subscriber: &mut HostSubscriber,
     rxbuf: &mut RxBuffer<2048>,
 ) -> Result<(), Error> {
-    let socket_write = async { wifi_tx_channel.receive().await };
+    let deadline = session.stream.as_ref().and_then(Stream::deadline);
+    let socket_write = async { subscriber.next_message().await };
     let socket_read = async { (socket.read(rxbuf.en_mut_block()).await).unwrap_or_default() };
+    let stream_flush = async move {
+        match deadline {
+            Some(deadline) => Timer::at(deadline).await,
+            None => pending().await,
+        }
+    };
 
-    // Wait for both and handle first event
-    match select(socket_write, socket_read).await {
-        Either::First(com_item) => {
+    // Wait for all and handle first event
+    match select3(socket_write, socket_read, stream_flush).await {
+        Either3::First(WaitResult::Message((target, com_item))) => {
+            if !target.includes(session.id) || !session.connected {
+                return Ok(());
+            }
+            if let (ComItem::ReceivedFrame(frame), Some(stream)) =
+                (&com_item, session.stream.as_mut())
+            {
+                stream.push(udp, frame).await;
+                return Ok(());
+            }
             let ser = com_item.serialize();
             match socket.write_all(ser.as_slice()).await {
                 Ok(()) => (),
                 Err(_) => error!("Socket write error"),
             };
         }
-        Either::Second(n) => {
+        Either3::First(WaitResult::Lagged(n)) => warn!("Session {}: lost {n} items", session.id),
+        Either3::Second(n) => {
             rxbuf.set_head(n);
             loop {
-                let mut de_ser = DeSer::<50>::default();
+                let mut de_ser = DeSer::<DATAGRAM_LEN>::default();
                 match rxbuf.read(&mut de_ser) {
                     Ok(()) => (),
                     Err(e) => match e {
@@ -105,7 +422,12 @@ async fn socket_write_read(
                     },
                 }
                 let item = ComItem::deserialize(&mut de_ser)?;
-                wifi_rx_channel.send(item).await;
+                session.receive(item).await?;
+            }
+        }
+        Either3::Third(()) => {
+            if let Some(stream) = session.stream.as_mut() {
+                stream.flush(udp).await;
             }
         }
     };